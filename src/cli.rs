@@ -1,14 +1,16 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use anyhow::anyhow;
 use clap::{Parser, Subcommand};
-use inkwell::targets::FileType;
+use inkwell::{memory_buffer::MemoryBuffer, targets::FileType};
 
 use crate::{
-    lexical_analysis,
-    object_file_generator::generate_object_file,
+    diagnostics::{Diagnostic, SourceFile},
+    lexical_analysis::{self, LexError},
+    object_file_generator::{generate_object_file, CodegenError},
     preprocessing,
-    semantic_analysis::{self, bitcode_to_string},
-    syntax_analysis,
+    semantic_analysis::{self, bitcode_to_string, SemaError},
+    syntax_analysis::{self, ParseError},
 };
 
 #[derive(Parser)]
@@ -16,6 +18,9 @@ use crate::{
 pub struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Print the LLVM module when the compiler hits an internal error
+    #[arg(long, global = true)]
+    dump_on_ice: bool,
 }
 
 #[derive(Subcommand)]
@@ -38,49 +43,96 @@ impl Cli {
     pub async fn execute(self) -> anyhow::Result<()> {
         match self.command {
             Commands::Preprocess { file } => {
-                let code = tokio::fs::read_to_string(file).await?;
-                let code = preprocessing::remove_comment(&code)?;
+                let source = read_source(&file).await?;
+                let code = report(&source, || preprocessing::remove_comment(source.content()))?;
                 println!("Preprocess result: {code}");
             }
             Commands::Lex { file } => {
-                let code = tokio::fs::read_to_string(file).await?;
-                let code = preprocessing::remove_comment(&code)?;
-                let tokens = lexical_analysis::extract_tokens(&code)?;
-                println!("Lexical analysis result: {tokens:?}");
+                let source = read_source(&file).await?;
+                let tokens = report(&source, || {
+                    let code = preprocessing::blank_comment(source.content())?;
+                    Ok(lexical_analysis::extract_tokens(&code)?)
+                })?;
+                println!("Lexical analysis result: {:?}", tokens.tokens);
             }
             Commands::Syntax { file } => {
-                let code = tokio::fs::read_to_string(file).await?;
-                let code = preprocessing::remove_comment(&code)?;
-                let tokens = lexical_analysis::extract_tokens(&code)?;
-                let unit = syntax_analysis::parse(&tokens)?;
+                let source = read_source(&file).await?;
+                let unit = report(&source, || {
+                    let code = preprocessing::blank_comment(source.content())?;
+                    let tokens = lexical_analysis::extract_tokens(&code)?;
+                    Ok(syntax_analysis::parse(&tokens)?)
+                })?;
                 println!("Syntax analysis result: {unit:#?}");
             }
             Commands::Semantic { file } => {
-                let code = tokio::fs::read_to_string(file).await?;
-                let code = preprocessing::remove_comment(&code)?;
-                let tokens = lexical_analysis::extract_tokens(&code)?;
-                let unit = syntax_analysis::parse(&tokens)?;
-                let bitcode = semantic_analysis::analysis(unit)?;
+                let source = read_source(&file).await?;
+                let bitcode = report(&source, || compile(&source, self.dump_on_ice))?;
                 let ir = bitcode_to_string(bitcode)?;
                 println!("Semantic analysis result: {ir}");
             }
             Commands::CompileBinary { file, output } => {
-                let code = tokio::fs::read_to_string(file).await?;
-                let code = preprocessing::remove_comment(&code)?;
-                let tokens = lexical_analysis::extract_tokens(&code)?;
-                let unit = syntax_analysis::parse(&tokens)?;
-                let bitcode = semantic_analysis::analysis(unit)?;
-                generate_object_file(bitcode, &output, FileType::Object)?;
+                let source = read_source(&file).await?;
+                report(&source, || {
+                    let bitcode = compile(&source, self.dump_on_ice)?;
+                    Ok(generate_object_file(bitcode, &output, FileType::Object)?)
+                })?;
             }
             Commands::CompileAssembly { file, output } => {
-                let code = tokio::fs::read_to_string(file).await?;
-                let code = preprocessing::remove_comment(&code)?;
-                let tokens = lexical_analysis::extract_tokens(&code)?;
-                let unit = syntax_analysis::parse(&tokens)?;
-                let bitcode = semantic_analysis::analysis(unit)?;
-                generate_object_file(bitcode, &output, FileType::Assembly)?;
+                let source = read_source(&file).await?;
+                report(&source, || {
+                    let bitcode = compile(&source, self.dump_on_ice)?;
+                    Ok(generate_object_file(bitcode, &output, FileType::Assembly)?)
+                })?;
             }
         }
         Ok(())
     }
 }
+
+async fn read_source(file: &Path) -> anyhow::Result<SourceFile> {
+    let code = tokio::fs::read_to_string(file).await?;
+    Ok(SourceFile::new(file.display().to_string(), code))
+}
+
+fn compile(source: &SourceFile, dump_on_ice: bool) -> anyhow::Result<MemoryBuffer> {
+    let code = preprocessing::blank_comment(source.content())?;
+    let tokens = lexical_analysis::extract_tokens(&code)?;
+    let unit = syntax_analysis::parse(&tokens)?;
+    let (bitcode, warnings) = semantic_analysis::analysis(unit, dump_on_ice)?;
+    for warning in &warnings {
+        eprintln!("{}", source.render(warning));
+    }
+    Ok(bitcode)
+}
+
+/// Diagnostics describing an error from one of the compiler phases.
+fn diagnostics(err: &anyhow::Error) -> Option<Vec<Diagnostic>> {
+    if let Some(diagnostic) = err.downcast_ref::<Diagnostic>() {
+        Some(vec![diagnostic.clone()])
+    } else if let Some(err) = err.downcast_ref::<LexError>() {
+        Some(vec![err.to_diagnostic()])
+    } else if let Some(err) = err.downcast_ref::<ParseError>() {
+        Some(err.to_diagnostics())
+    } else if let Some(err) = err.downcast_ref::<SemaError>() {
+        Some(vec![err.to_diagnostic()])
+    } else {
+        err.downcast_ref::<CodegenError>()
+            .map(|err| vec![err.to_diagnostic()])
+    }
+}
+
+/// Print the diagnostics carried by an error, if any, against the source file.
+fn report<T>(source: &SourceFile, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    f().map_err(|err| {
+        let Some(diagnostics) = diagnostics(&err) else {
+            return err;
+        };
+        for diagnostic in &diagnostics {
+            eprintln!("{}", source.render(diagnostic));
+        }
+        match diagnostics.len() {
+            1 => anyhow!("could not compile due to previous error"),
+            n => anyhow!("could not compile due to {n} previous errors"),
+        }
+    })
+}
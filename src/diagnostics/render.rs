@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use super::{Diagnostic, Label, Span};
+
+/// Source text of a file together with its name, used to render diagnostics.
+pub struct SourceFile {
+    name: String,
+    content: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, content: impl Into<String>) -> Self {
+        let content = content.into();
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceFile {
+            name: name.into(),
+            content,
+            line_starts,
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Zero-based line index of a byte offset.
+    fn line_index(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.content.len(), |next| next - 1);
+        self.content[start..end].trim_end_matches('\r')
+    }
+
+    /// One-based `(line, column)` of a byte offset.
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len());
+        let line = self.line_index(offset);
+        let column = self.content[self.line_starts[line]..offset].chars().count();
+        (line + 1, column + 1)
+    }
+
+    /// Render a diagnostic in the style of `rustc`:
+    ///
+    /// ```text
+    /// error: expected `;`, found `}`
+    ///  --> main.c:3:1
+    ///   |
+    /// 3 | }
+    ///   | ^ expected `;`
+    /// ```
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "{diagnostic}");
+        let mut labels: Vec<&Label> = diagnostic.labels.iter().collect();
+        labels.sort_by_key(|label| (label.span.start, !label.primary));
+        let location = diagnostic
+            .primary_span()
+            .or_else(|| labels.first().map(|label| label.span));
+        let last_line = labels
+            .iter()
+            .map(|label| self.line_index(label.span.start.min(self.content.len())) + 1)
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(last_line.to_string().len());
+        if let Some(Span { start, .. }) = location {
+            let (line, column) = self.location(start);
+            let _ = writeln!(output, "{gutter}--> {}:{line}:{column}", self.name);
+            let _ = writeln!(output, "{gutter} |");
+            let mut previous_line = None;
+            for label in &labels {
+                let line = self.line_index(label.span.start.min(self.content.len()));
+                if previous_line != Some(line) {
+                    if matches!(previous_line, Some(previous) if line > previous + 1) {
+                        let _ = writeln!(output, "...");
+                    }
+                    let text = self.line_text(line);
+                    let _ = writeln!(
+                        output,
+                        "{:>width$} | {text}",
+                        line + 1,
+                        width = gutter.len()
+                    );
+                    previous_line = Some(line);
+                }
+                let _ = writeln!(output, "{gutter} | {}", self.underline(label));
+            }
+        }
+        if !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+            if location.is_some() {
+                let _ = writeln!(output, "{gutter} |");
+            }
+            for note in &diagnostic.notes {
+                let _ = writeln!(output, "{gutter} = note: {note}");
+            }
+            for help in &diagnostic.help {
+                let _ = writeln!(output, "{gutter} = help: {help}");
+            }
+        }
+        output
+    }
+
+    fn underline(&self, label: &Label) -> String {
+        let start = label.span.start.min(self.content.len());
+        let line = self.line_index(start);
+        let line_start = self.line_starts[line];
+        let text = self.line_text(line);
+        let line_end = line_start + text.len();
+        let padding: String = self.content[line_start..start]
+            .chars()
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let end = label.span.end.clamp(start, line_end);
+        let width = self.content[start..end].chars().count().max(1);
+        let mark = if label.primary { "^" } else { "-" };
+        let mut underline = format!("{padding}{}", mark.repeat(width));
+        if !label.message.is_empty() {
+            let _ = write!(underline, " {}", label.message);
+        }
+        underline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_is_one_based() {
+        let source = SourceFile::new("test.c", "int a;\nint b;\n");
+        assert_eq!(source.location(0), (1, 1));
+        assert_eq!(source.location(4), (1, 5));
+        assert_eq!(source.location(7), (2, 1));
+        assert_eq!(source.location(14), (3, 1));
+    }
+
+    #[test]
+    fn render_primary_label() {
+        let source = SourceFile::new("test.c", "int main(void) {\n    return 0\n}\n");
+        let diagnostic = Diagnostic::error("expected `;`, found `}`")
+            .with_primary(Span::new(30, 31), "expected `;`");
+        assert_eq!(
+            source.render(&diagnostic),
+            "error: expected `;`, found `}`\n \
+             --> test.c:3:1\n  \
+             |\n\
+             3 | }\n  \
+             | ^ expected `;`\n"
+        );
+    }
+
+    #[test]
+    fn render_secondary_label_notes_and_help() {
+        let source = SourceFile::new("test.c", "int a;\nint a;\n");
+        let diagnostic = Diagnostic::error("redefinition of `a`")
+            .with_primary(Span::new(11, 12), "redefined here")
+            .with_secondary(Span::new(4, 5), "previous definition here")
+            .with_note("`a` must be defined only once")
+            .with_help("rename one of the variables");
+        assert_eq!(
+            source.render(&diagnostic),
+            "error: redefinition of `a`\n \
+             --> test.c:2:5\n  \
+             |\n\
+             1 | int a;\n  \
+             |     - previous definition here\n\
+             2 | int a;\n  \
+             |     ^ redefined here\n  \
+             |\n  \
+             = note: `a` must be defined only once\n  \
+             = help: rename one of the variables\n"
+        );
+    }
+}
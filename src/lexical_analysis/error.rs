@@ -0,0 +1,25 @@
+use crate::diagnostics::{Diagnostic, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LexError {
+    #[error("invalid identifier `{identifier}`")]
+    InvalidIdentifier { identifier: String, span: Span },
+}
+
+impl LexError {
+    /// Stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::InvalidIdentifier { .. } => "E0001",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string()).with_code(self.code());
+        match self {
+            LexError::InvalidIdentifier { span, .. } => {
+                diagnostic.with_primary(*span, "identifier cannot start with numeric characters")
+            }
+        }
+    }
+}
@@ -1,9 +1,13 @@
+mod error;
 mod token_matcher;
 mod tokens;
 
+pub use error::LexError;
 use token_matcher::*;
 pub use tokens::*;
 
+use crate::diagnostics::Span;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 struct Position {
     line: usize,
@@ -14,7 +18,7 @@ impl Position {
     fn from_cursor(input: &str, cursor: usize) -> Self {
         let mut line: usize = 0;
         let mut column: usize = 0;
-        for ch in input.chars().take(cursor) {
+        for ch in input[..cursor].chars() {
             if ch == '\n' {
                 line += 1;
                 column = 0;
@@ -37,21 +41,30 @@ enum PositionOperation {
     Next(usize),
 }
 
-fn checked_identifier(identifier: &str) -> anyhow::Result<String> {
+fn checked_identifier(identifier: &str, span: Span) -> Result<String, LexError> {
     let identifier: Vec<_> = identifier.chars().collect();
     match &identifier[..] {
         [] => unreachable!(),
-        [ch, ..] if ch.is_numeric() => {
-            anyhow::bail!("Identifier cannot start with numeric characters")
-        }
+        [ch, ..] if ch.is_numeric() => Err(LexError::InvalidIdentifier {
+            identifier: identifier.into_iter().collect(),
+            span,
+        }),
         _ => Ok(identifier.into_iter().collect()),
     }
 }
 
-pub fn extract_tokens(input: &str) -> anyhow::Result<Vec<Token>> {
+/// Tokens of a source file. `spans[i]` is the location of `tokens[i]`.
+#[derive(Debug)]
+pub struct Tokens {
+    pub tokens: Vec<Token>,
+    pub spans: Vec<Span>,
+}
+
+pub fn extract_tokens(input: &str) -> Result<Tokens, LexError> {
     let mut tokens = Vec::new();
+    let mut spans = Vec::new();
     let mut position = Position { column: 0, line: 0 };
-    let mut it = input.chars().enumerate();
+    let mut it = input.char_indices();
 
     while let Some((cursor, ch)) = it.next() {
         use PositionOperation::*;
@@ -59,10 +72,34 @@ pub fn extract_tokens(input: &str) -> anyhow::Result<Vec<Token>> {
         let position_operation = match ch {
             '\n' => NewLine,
             ch if ch.is_ascii_whitespace() => Next(1),
+            ch if ch.is_ascii_alphanumeric() || ch == '_' => {
+                // scan the whole word before looking for a keyword, so that
+                // e.g. `integer` is not split into `int` and `eger`
+                let word_len = input[cursor..]
+                    .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                    .unwrap_or(input.len() - cursor);
+                let word = &input[cursor..cursor + word_len];
+                let span = Span::new(cursor, cursor + word_len);
+                let token = match TOKEN_MATCHER.get_token(word) {
+                    Some((keyword, len)) if len == word_len => keyword,
+                    _ => match Constant::new(word) {
+                        Ok(constant) => Token::Constant(constant),
+                        Err(()) => Token::Identifier(checked_identifier(word, span)?),
+                    },
+                };
+                tracing::trace!("Word: {token:?}");
+                tokens.push(token);
+                spans.push(span);
+                for _ in 0..(word_len - 1) {
+                    let _ = it.next();
+                }
+                Next(word_len)
+            }
             _ => {
                 if let Some((token, token_len)) = TOKEN_MATCHER.get_token(&input[cursor..]) {
                     tracing::trace!("Token: {token:?}");
                     tokens.push(token);
+                    spans.push(Span::new(cursor, cursor + token_len));
                     for _ in 0..(token_len - 1) {
                         let _ = it.next();
                     }
@@ -81,19 +118,22 @@ pub fn extract_tokens(input: &str) -> anyhow::Result<Vec<Token>> {
                         end = i + 1;
                     }
                     let buffer = &buffer[..end];
+                    let span = Span::new(cursor, cursor + buffer.len());
                     if let Ok(constant) = Constant::new(buffer) {
                         let token = Token::Constant(constant);
                         tracing::trace!("Constant: {token:?}");
                         tokens.push(token);
                     } else {
-                        let token = Token::Identifier(checked_identifier(buffer)?);
+                        let token = Token::Identifier(checked_identifier(buffer, span)?);
                         tracing::trace!("Identifier: {token:?}");
                         tokens.push(token);
                     }
-                    for _ in 0..(buffer.len() - 1) {
+                    spans.push(span);
+                    let buffer_len = buffer.chars().count();
+                    for _ in 0..(buffer_len - 1) {
                         let _ = it.next();
                     }
-                    Next(buffer.len())
+                    Next(buffer_len)
                 }
             }
         };
@@ -108,7 +148,8 @@ pub fn extract_tokens(input: &str) -> anyhow::Result<Vec<Token>> {
         }
     }
     tokens.push(Token::End);
-    Ok(tokens)
+    spans.push(Span::new(input.len(), input.len()));
+    Ok(Tokens { tokens, spans })
 }
 
 #[cfg(test)]
@@ -123,4 +164,57 @@ mod tests {
         assert_eq!(position.line, 0);
         assert_eq!(position.column, 0);
     }
+
+    #[test]
+    fn keyword_prefix_is_identifier() {
+        let tokens = extract_tokens("integer done format do_it for").unwrap();
+        assert_eq!(
+            tokens.tokens,
+            vec![
+                Token::Identifier("integer".to_string()),
+                Token::Identifier("done".to_string()),
+                Token::Identifier("format".to_string()),
+                Token::Identifier("do_it".to_string()),
+                Token::Keyword(Keyword::For),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_constant_types() {
+        let tokens =
+            extract_tokens("017 0x7fffffff 2147483648 0xffffffff 10u 10l 0x8000000000000000 10ULL")
+                .unwrap();
+        assert_eq!(
+            tokens.tokens,
+            vec![
+                Token::Constant(Constant::Int(15)),
+                Token::Constant(Constant::Int(i32::MAX)),
+                Token::Constant(Constant::Long(2147483648)),
+                Token::Constant(Constant::UnsignedInt(u32::MAX)),
+                Token::Constant(Constant::UnsignedInt(10)),
+                Token::Constant(Constant::Long(10)),
+                Token::Constant(Constant::UnsignedLong(1 << 63)),
+                Token::Constant(Constant::UnsignedLongLong(10)),
+                Token::End,
+            ]
+        );
+        for invalid in ["08", "0x", "10lul", "18446744073709551616"] {
+            assert!(extract_tokens(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn invalid_identifier() {
+        let err = extract_tokens("int 1a;").unwrap_err();
+        assert_eq!(
+            err,
+            LexError::InvalidIdentifier {
+                identifier: "1a".to_string(),
+                span: Span::new(4, 6),
+            }
+        );
+        assert_eq!(err.code(), "E0001");
+    }
 }
@@ -2,7 +2,7 @@ use super::*;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
-pub(in super) struct TokenMatcher {
+pub(super) struct TokenMatcher {
     pub(self) child: HashMap<char, TokenMatcher>,
     pub(self) current_token: Option<Token>,
 }
@@ -85,6 +85,10 @@ lazy_static! {
         match TokenMatcherBuilder::new()
             .with("(", Token::Symbol(Symbol::LeftParen))
             .with(")", Token::Symbol(Symbol::RightParen))
+            .with("[", Token::Symbol(Symbol::LeftBracket))
+            .with("]", Token::Symbol(Symbol::RightBracket))
+            .with(".", Token::Symbol(Symbol::Period))
+            .with("->", Token::Symbol(Symbol::Arrow))
             .with("{", Token::Symbol(Symbol::LeftBrace))
             .with("}", Token::Symbol(Symbol::RightBrace))
             .with("+", Token::Symbol(Symbol::Plus))
@@ -100,15 +104,54 @@ lazy_static! {
             .with(">=", Token::Symbol(Symbol::GreaterEqual))
             .with("||", Token::Symbol(Symbol::Or))
             .with("&&", Token::Symbol(Symbol::And))
+            .with("&", Token::Symbol(Symbol::Amp))
+            .with("|", Token::Symbol(Symbol::Pipe))
+            .with("^", Token::Symbol(Symbol::Caret))
+            .with("~", Token::Symbol(Symbol::Tilde))
+            .with("<<", Token::Symbol(Symbol::LessLess))
+            .with(">>", Token::Symbol(Symbol::GreaterGreater))
             .with("!", Token::Symbol(Symbol::Exclaim))
             .with("=", Token::Symbol(Symbol::Equal))
+            .with("++", Token::Symbol(Symbol::PlusPlus))
+            .with("--", Token::Symbol(Symbol::MinusMinus))
+            .with("+=", Token::Symbol(Symbol::PlusEqual))
+            .with("-=", Token::Symbol(Symbol::MinusEqual))
+            .with("*=", Token::Symbol(Symbol::StarEqual))
+            .with("/=", Token::Symbol(Symbol::SlashEqual))
+            .with("%=", Token::Symbol(Symbol::ModuloEqual))
+            .with("&=", Token::Symbol(Symbol::AmpEqual))
+            .with("|=", Token::Symbol(Symbol::PipeEqual))
+            .with("^=", Token::Symbol(Symbol::CaretEqual))
+            .with("<<=", Token::Symbol(Symbol::LessLessEqual))
+            .with(">>=", Token::Symbol(Symbol::GreaterGreaterEqual))
+            .with("?", Token::Symbol(Symbol::Question))
+            .with(":", Token::Symbol(Symbol::Colon))
             .with(",", Token::Symbol(Symbol::Comma))
             .with(";", Token::Symbol(Symbol::Semicolon))
             .with("int", Token::Keyword(Keyword::Int))
             .with("void", Token::Keyword(Keyword::Void))
+            .with("_Bool", Token::Keyword(Keyword::Bool))
+            .with("char", Token::Keyword(Keyword::Char))
+            .with("short", Token::Keyword(Keyword::Short))
+            .with("long", Token::Keyword(Keyword::Long))
+            .with("signed", Token::Keyword(Keyword::Signed))
+            .with("unsigned", Token::Keyword(Keyword::Unsigned))
+            .with("struct", Token::Keyword(Keyword::Struct))
+            .with("union", Token::Keyword(Keyword::Union))
+            .with("enum", Token::Keyword(Keyword::Enum))
+            .with("typedef", Token::Keyword(Keyword::Typedef))
+            .with("sizeof", Token::Keyword(Keyword::Sizeof))
             .with("if", Token::Keyword(Keyword::If))
             .with("else", Token::Keyword(Keyword::Else))
             .with("while", Token::Keyword(Keyword::While))
+            .with("do", Token::Keyword(Keyword::Do))
+            .with("for", Token::Keyword(Keyword::For))
+            .with("break", Token::Keyword(Keyword::Break))
+            .with("continue", Token::Keyword(Keyword::Continue))
+            .with("switch", Token::Keyword(Keyword::Switch))
+            .with("case", Token::Keyword(Keyword::Case))
+            .with("default", Token::Keyword(Keyword::Default))
+            .with("goto", Token::Keyword(Keyword::Goto))
             .with("return", Token::Keyword(Keyword::Return))
             .with("true", Token::Constant(Constant::Int(1)))
             .with("false", Token::Constant(Constant::Int(0)))
@@ -141,7 +184,7 @@ mod tests {
             if current.current_token.is_none() {
                 assert!(!current.child.is_empty())
             }
-            for (_, child) in &current.child {
+            for child in current.child.values() {
                 right_structure(child);
             }
         }
@@ -156,7 +199,7 @@ mod tests {
                 assert!(!all_tokens.contains(token));
                 all_tokens.insert(token.to_owned());
             }
-            for (_, child) in &current.child {
+            for child in current.child.values() {
                 no_duplicate(child, all_tokens);
             }
         }
@@ -183,6 +226,10 @@ mod tests {
         };
         test_token("(", Token::Symbol(Symbol::LeftParen));
         test_token(")", Token::Symbol(Symbol::RightParen));
+        test_token("[", Token::Symbol(Symbol::LeftBracket));
+        test_token("]", Token::Symbol(Symbol::RightBracket));
+        test_token(".", Token::Symbol(Symbol::Period));
+        test_token("->", Token::Symbol(Symbol::Arrow));
         test_token("{", Token::Symbol(Symbol::LeftBrace));
         test_token("}", Token::Symbol(Symbol::RightBrace));
         test_token("+", Token::Symbol(Symbol::Plus));
@@ -198,15 +245,54 @@ mod tests {
         test_token(">=", Token::Symbol(Symbol::GreaterEqual));
         test_token("||", Token::Symbol(Symbol::Or));
         test_token("&&", Token::Symbol(Symbol::And));
+        test_token("&", Token::Symbol(Symbol::Amp));
+        test_token("|", Token::Symbol(Symbol::Pipe));
+        test_token("^", Token::Symbol(Symbol::Caret));
+        test_token("~", Token::Symbol(Symbol::Tilde));
+        test_token("<<", Token::Symbol(Symbol::LessLess));
+        test_token(">>", Token::Symbol(Symbol::GreaterGreater));
         test_token("!", Token::Symbol(Symbol::Exclaim));
         test_token("=", Token::Symbol(Symbol::Equal));
+        test_token("++", Token::Symbol(Symbol::PlusPlus));
+        test_token("--", Token::Symbol(Symbol::MinusMinus));
+        test_token("+=", Token::Symbol(Symbol::PlusEqual));
+        test_token("-=", Token::Symbol(Symbol::MinusEqual));
+        test_token("*=", Token::Symbol(Symbol::StarEqual));
+        test_token("/=", Token::Symbol(Symbol::SlashEqual));
+        test_token("%=", Token::Symbol(Symbol::ModuloEqual));
+        test_token("&=", Token::Symbol(Symbol::AmpEqual));
+        test_token("|=", Token::Symbol(Symbol::PipeEqual));
+        test_token("^=", Token::Symbol(Symbol::CaretEqual));
+        test_token("<<=", Token::Symbol(Symbol::LessLessEqual));
+        test_token(">>=", Token::Symbol(Symbol::GreaterGreaterEqual));
+        test_token("?", Token::Symbol(Symbol::Question));
+        test_token(":", Token::Symbol(Symbol::Colon));
         test_token(",", Token::Symbol(Symbol::Comma));
         test_token(";", Token::Symbol(Symbol::Semicolon));
         test_token("int", Token::Keyword(Keyword::Int));
         test_token("void", Token::Keyword(Keyword::Void));
+        test_token("_Bool", Token::Keyword(Keyword::Bool));
+        test_token("char", Token::Keyword(Keyword::Char));
+        test_token("short", Token::Keyword(Keyword::Short));
+        test_token("long", Token::Keyword(Keyword::Long));
+        test_token("signed", Token::Keyword(Keyword::Signed));
+        test_token("unsigned", Token::Keyword(Keyword::Unsigned));
+        test_token("struct", Token::Keyword(Keyword::Struct));
+        test_token("union", Token::Keyword(Keyword::Union));
+        test_token("enum", Token::Keyword(Keyword::Enum));
+        test_token("typedef", Token::Keyword(Keyword::Typedef));
+        test_token("sizeof", Token::Keyword(Keyword::Sizeof));
         test_token("if", Token::Keyword(Keyword::If));
         test_token("else", Token::Keyword(Keyword::Else));
         test_token("while", Token::Keyword(Keyword::While));
+        test_token("do", Token::Keyword(Keyword::Do));
+        test_token("for", Token::Keyword(Keyword::For));
+        test_token("break", Token::Keyword(Keyword::Break));
+        test_token("continue", Token::Keyword(Keyword::Continue));
+        test_token("switch", Token::Keyword(Keyword::Switch));
+        test_token("case", Token::Keyword(Keyword::Case));
+        test_token("default", Token::Keyword(Keyword::Default));
+        test_token("goto", Token::Keyword(Keyword::Goto));
         test_token("return", Token::Keyword(Keyword::Return));
         test_token("true", Token::Constant(Constant::Int(1)));
         test_token("false", Token::Constant(Constant::Int(0)));
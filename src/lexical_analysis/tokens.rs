@@ -13,6 +13,10 @@ pub enum Symbol {
     RightBrace,
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
+    Period,
+    Arrow,
     Plus,
     Minus,
     Star,
@@ -26,8 +30,28 @@ pub enum Symbol {
     GreaterEqual,
     Or,
     And,
+    Amp,
+    Pipe,
+    Caret,
+    Tilde,
+    LessLess,
+    GreaterGreater,
     Exclaim,
     Equal,
+    PlusPlus,
+    MinusMinus,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    ModuloEqual,
+    AmpEqual,
+    PipeEqual,
+    CaretEqual,
+    LessLessEqual,
+    GreaterGreaterEqual,
+    Question,
+    Colon,
     Comma,
     Semicolon,
 }
@@ -36,22 +60,227 @@ pub enum Symbol {
 pub enum Keyword {
     Int,
     Void,
+    Bool,
+    Char,
+    Short,
+    Long,
+    Signed,
+    Unsigned,
+    Struct,
+    Union,
+    Enum,
+    Typedef,
+    Sizeof,
     If,
     Else,
     While,
+    Do,
+    For,
+    Break,
+    Continue,
+    Switch,
+    Case,
+    Default,
+    Goto,
     Return,
 }
 
+/// An integer constant, with the first type that can represent it among
+/// those its base and suffix allow.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Constant {
     Int(i32),
+    UnsignedInt(u32),
+    Long(i64),
+    UnsignedLong(u64),
+    LongLong(i64),
+    UnsignedLongLong(u64),
 }
 
 impl Constant {
+    /// Parse a decimal, octal or hexadecimal integer constant, with an
+    /// optional `u` and `l` or `ll` suffix.
     pub fn new(input: &str) -> Result<Self, ()> {
-        if let Ok(value) = input.parse::<i32>() {
-            return Ok(Constant::Int(value));
+        let (digits, suffix) = input.split_at(input.trim_end_matches(['u', 'U', 'l', 'L']).len());
+        // how many `long`s the suffix asks for, and whether it is unsigned
+        let (long, unsigned) = match suffix {
+            "" => (0, false),
+            "u" | "U" => (0, true),
+            "l" | "L" => (1, false),
+            "ul" | "uL" | "Ul" | "UL" | "lu" | "lU" | "Lu" | "LU" => (1, true),
+            "ll" | "LL" => (2, false),
+            "ull" | "uLL" | "Ull" | "ULL" | "llu" | "llU" | "LLu" | "LLU" => (2, true),
+            _ => return Err(()),
+        };
+        let (radix, digits) = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+            Some(digits) => (16, digits),
+            None if digits.len() > 1 && digits.starts_with('0') => (8, &digits[1..]),
+            None => (10, digits),
+        };
+        if digits.is_empty() || !digits.chars().all(|ch| ch.is_digit(radix)) {
+            return Err(());
+        }
+        let value = u64::from_str_radix(digits, radix).map_err(|_| ())?;
+        // a decimal constant without `u` is never unsigned
+        let types = [
+            (0, false),
+            (0, true),
+            (1, false),
+            (1, true),
+            (2, false),
+            (2, true),
+        ];
+        types
+            .into_iter()
+            .filter(|&(ty_long, ty_unsigned)| {
+                ty_long >= long
+                    && (ty_unsigned || !unsigned)
+                    && (!ty_unsigned || unsigned || radix != 10)
+            })
+            .find_map(|ty| match ty {
+                (0, false) => i32::try_from(value).ok().map(Constant::Int),
+                (0, true) => u32::try_from(value).ok().map(Constant::UnsignedInt),
+                (1, false) => i64::try_from(value).ok().map(Constant::Long),
+                (1, true) => Some(Constant::UnsignedLong(value)),
+                (2, false) => i64::try_from(value).ok().map(Constant::LongLong),
+                _ => Some(Constant::UnsignedLongLong(value)),
+            })
+            .ok_or(())
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Identifier(identifier) => write!(f, "{identifier}"),
+            Token::Constant(constant) => write!(f, "{constant}"),
+            Token::Symbol(symbol) => write!(f, "{symbol}"),
+            Token::Keyword(keyword) => write!(f, "{keyword}"),
+            Token::End => write!(f, "<end of file>"),
+        }
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Symbol::LeftBrace => "{",
+            Symbol::RightBrace => "}",
+            Symbol::LeftParen => "(",
+            Symbol::RightParen => ")",
+            Symbol::LeftBracket => "[",
+            Symbol::RightBracket => "]",
+            Symbol::Period => ".",
+            Symbol::Arrow => "->",
+            Symbol::Plus => "+",
+            Symbol::Minus => "-",
+            Symbol::Star => "*",
+            Symbol::Slash => "/",
+            Symbol::Modulo => "%",
+            Symbol::EqualEqual => "==",
+            Symbol::ExclaimEqual => "!=",
+            Symbol::Less => "<",
+            Symbol::LessEqual => "<=",
+            Symbol::Greater => ">",
+            Symbol::GreaterEqual => ">=",
+            Symbol::Or => "||",
+            Symbol::And => "&&",
+            Symbol::Amp => "&",
+            Symbol::Pipe => "|",
+            Symbol::Caret => "^",
+            Symbol::Tilde => "~",
+            Symbol::LessLess => "<<",
+            Symbol::GreaterGreater => ">>",
+            Symbol::Exclaim => "!",
+            Symbol::Equal => "=",
+            Symbol::PlusPlus => "++",
+            Symbol::MinusMinus => "--",
+            Symbol::PlusEqual => "+=",
+            Symbol::MinusEqual => "-=",
+            Symbol::StarEqual => "*=",
+            Symbol::SlashEqual => "/=",
+            Symbol::ModuloEqual => "%=",
+            Symbol::AmpEqual => "&=",
+            Symbol::PipeEqual => "|=",
+            Symbol::CaretEqual => "^=",
+            Symbol::LessLessEqual => "<<=",
+            Symbol::GreaterGreaterEqual => ">>=",
+            Symbol::Question => "?",
+            Symbol::Colon => ":",
+            Symbol::Comma => ",",
+            Symbol::Semicolon => ";",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl Keyword {
+    /// Whether the keyword can start a declaration.
+    pub fn starts_declaration(&self) -> bool {
+        self.is_type_specifier() || *self == Keyword::Typedef
+    }
+
+    /// Whether the keyword can start the type of a declaration.
+    pub fn is_type_specifier(&self) -> bool {
+        matches!(
+            self,
+            Keyword::Int
+                | Keyword::Void
+                | Keyword::Bool
+                | Keyword::Char
+                | Keyword::Short
+                | Keyword::Long
+                | Keyword::Signed
+                | Keyword::Unsigned
+                | Keyword::Struct
+                | Keyword::Union
+                | Keyword::Enum
+        )
+    }
+}
+
+impl std::fmt::Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = match self {
+            Keyword::Int => "int",
+            Keyword::Void => "void",
+            Keyword::Bool => "_Bool",
+            Keyword::Char => "char",
+            Keyword::Short => "short",
+            Keyword::Long => "long",
+            Keyword::Signed => "signed",
+            Keyword::Unsigned => "unsigned",
+            Keyword::Struct => "struct",
+            Keyword::Union => "union",
+            Keyword::Enum => "enum",
+            Keyword::Typedef => "typedef",
+            Keyword::Sizeof => "sizeof",
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::While => "while",
+            Keyword::Do => "do",
+            Keyword::For => "for",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::Switch => "switch",
+            Keyword::Case => "case",
+            Keyword::Default => "default",
+            Keyword::Goto => "goto",
+            Keyword::Return => "return",
+        };
+        write!(f, "{keyword}")
+    }
+}
+
+impl std::fmt::Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{value}"),
+            Constant::UnsignedInt(value) => write!(f, "{value}u"),
+            Constant::Long(value) => write!(f, "{value}l"),
+            Constant::UnsignedLong(value) => write!(f, "{value}ul"),
+            Constant::LongLong(value) => write!(f, "{value}ll"),
+            Constant::UnsignedLongLong(value) => write!(f, "{value}ull"),
         }
-        Err(())
     }
 }
@@ -1,4 +1,5 @@
 mod cli;
+mod diagnostics;
 mod lexical_analysis;
 mod object_file_generator;
 mod preprocessing;
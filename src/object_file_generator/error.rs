@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use crate::diagnostics::Diagnostic;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CodegenError {
+    #[error("unsupported target `{triple}`: {message}")]
+    UnknownTarget { triple: String, message: String },
+    #[error("failed to create target machine for `{triple}`")]
+    TargetMachine { triple: String },
+    #[error("invalid bitcode: {0}")]
+    InvalidBitcode(String),
+    #[error("failed to write `{}`: {message}", .path.display())]
+    WriteFile { path: PathBuf, message: String },
+}
+
+impl CodegenError {
+    /// Stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CodegenError::UnknownTarget { .. } => "E0301",
+            CodegenError::TargetMachine { .. } => "E0302",
+            CodegenError::InvalidBitcode(_) => "E0303",
+            CodegenError::WriteFile { .. } => "E0304",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.to_string()).with_code(self.code())
+    }
+}
@@ -1,6 +1,7 @@
+mod error;
+
 use std::path::Path;
 
-use anyhow::anyhow;
 use inkwell::{
     context::Context,
     memory_buffer::MemoryBuffer,
@@ -8,15 +9,20 @@ use inkwell::{
     OptimizationLevel,
 };
 
+pub use error::CodegenError;
+
 pub fn generate_object_file(
     bitcode: MemoryBuffer,
     output_path: &Path,
     file_type: FileType,
-) -> anyhow::Result<()> {
+) -> Result<(), CodegenError> {
     let triple = TargetMachine::get_default_triple();
     Target::initialize_all(&InitializationConfig::default());
     let target =
-        Target::from_triple(&triple).map_err(|llvm_string| anyhow!(llvm_string.to_string()))?;
+        Target::from_triple(&triple).map_err(|llvm_string| CodegenError::UnknownTarget {
+            triple: triple.to_string(),
+            message: llvm_string.to_string(),
+        })?;
     let Some(target_machine) = target.create_target_machine(
         &triple,
         "generic",
@@ -25,14 +31,19 @@ pub fn generate_object_file(
         RelocMode::Static,
         CodeModel::Default,
     ) else {
-        anyhow::bail!("Failed to create target machine");
+        return Err(CodegenError::TargetMachine {
+            triple: triple.to_string(),
+        });
     };
     let context = Context::create();
     let module = context
         .create_module_from_ir(bitcode)
-        .map_err(|llvm_string| anyhow!(llvm_string.to_string()))?;
+        .map_err(|llvm_string| CodegenError::InvalidBitcode(llvm_string.to_string()))?;
     target_machine
         .write_to_file(&module, file_type, output_path)
-        .map_err(|llvm_string| anyhow!(llvm_string.to_string()))?;
+        .map_err(|llvm_string| CodegenError::WriteFile {
+            path: output_path.to_owned(),
+            message: llvm_string.to_string(),
+        })?;
     Ok(())
 }
@@ -1,3 +1,3 @@
 mod remove_comment;
 
-pub use remove_comment::remove_comment;
+pub use remove_comment::{blank_comment, remove_comment};
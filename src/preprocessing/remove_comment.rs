@@ -1,5 +1,7 @@
 use std::iter;
 
+use crate::diagnostics::{Diagnostic, Span};
+
 enum CommentState {
     NotComment,
     Slash,
@@ -11,11 +13,22 @@ enum CommentState {
 }
 
 pub fn remove_comment(input: &str) -> anyhow::Result<String> {
+    strip_comment(input, false)
+}
+
+/// Like [`remove_comment`], but replaces every comment character with spaces
+/// (keeping newlines), so byte offsets still point into the original source.
+pub fn blank_comment(input: &str) -> anyhow::Result<String> {
+    strip_comment(input, true)
+}
+
+fn strip_comment(input: &str, keep_layout: bool) -> anyhow::Result<String> {
     use CommentState::*;
     let mut output = String::new();
     let mut state = CommentState::NotComment;
     let mut buffer = String::new();
-    for ch in input.chars() {
+    let mut comment_start = 0;
+    for (cursor, ch) in input.char_indices() {
         state = match (state, ch) {
             (NotComment, '/') => Slash,
             (NotComment, _) => NotComment,
@@ -34,15 +47,31 @@ pub fn remove_comment(input: &str) -> anyhow::Result<String> {
             (BlockEnd, _) => NotComment,
         };
         match state {
-            Slash => buffer.push(ch),
+            Slash => {
+                comment_start = cursor;
+                buffer.push(ch)
+            }
             NotComment | LineEnd => output.extend(buffer.drain(..).chain(iter::once(ch))),
+            _ if keep_layout => {
+                let blank = |ch: char| match ch {
+                    '\n' => "\n".to_string(),
+                    ch => " ".repeat(ch.len_utf8()),
+                };
+                let blanked: String = buffer.drain(..).chain(iter::once(ch)).map(blank).collect();
+                output.push_str(&blanked);
+            }
             _ => buffer.clear(),
         }
     }
     output.extend(buffer.drain(..));
     match state {
         BlockInnerStar | BlockInner => {
-            anyhow::bail!("Unfinished block comment")
+            anyhow::bail!(
+                Diagnostic::error("unterminated block comment").with_primary(
+                    Span::new(comment_start, comment_start + 2),
+                    "comment starts here"
+                )
+            )
         }
         _ => Ok(output),
     }
@@ -81,4 +110,12 @@ mod tests {
         assert_eq!("", remove_comment("/* \n\n\n */").unwrap());
         assert_eq!("", remove_comment("/* \n// \n// */").unwrap());
     }
+
+    #[test]
+    fn blank_comment_keeps_layout() {
+        assert_eq!("a  \nb", blank_comment("a//\nb").unwrap());
+        assert_eq!("a   \n  b", blank_comment("a/* \n*/b").unwrap());
+        assert_eq!("a /     b", blank_comment("a / /**/b").unwrap());
+        assert!(blank_comment("/*").is_err());
+    }
 }
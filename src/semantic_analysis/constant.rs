@@ -0,0 +1,241 @@
+use crate::{
+    lexical_analysis::Constant,
+    syntax_analysis::{
+        expression::{BinaryOperator, Expression, PrefixUnaryOperator, RefOrCall},
+        types::{IntegerKind, IntegerType, Type},
+    },
+};
+
+use super::{
+    types::{common_type, promoted, Value, SIZE_T},
+    SemaError, IR,
+};
+
+/// Value of an integer constant expression, with its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct IntegerConstant {
+    pub(super) value: i128,
+    pub(super) ty: IntegerType,
+}
+
+impl IntegerConstant {
+    /// `value` converted to `ty`: reduced modulo 2 to the width of `ty` if
+    /// it does not fit, and 0 or 1 for `_Bool`.
+    pub(super) fn new(value: i128, ty: IntegerType) -> Self {
+        let bits = ty.bits();
+        let value = match (ty.kind, ty.signed) {
+            (IntegerKind::Bool, _) => i128::from(value != 0),
+            (_, false) => value & ((1 << bits) - 1),
+            (_, true) => (value << (128 - bits)) >> (128 - bits),
+        };
+        IntegerConstant { value, ty }
+    }
+}
+
+impl From<&Constant> for IntegerConstant {
+    fn from(constant: &Constant) -> Self {
+        let (value, kind, signed) = match *constant {
+            Constant::Int(value) => (i128::from(value), IntegerKind::Int, true),
+            Constant::UnsignedInt(value) => (i128::from(value), IntegerKind::Int, false),
+            Constant::Long(value) => (i128::from(value), IntegerKind::Long, true),
+            Constant::UnsignedLong(value) => (i128::from(value), IntegerKind::Long, false),
+            Constant::LongLong(value) => (i128::from(value), IntegerKind::LongLong, true),
+            Constant::UnsignedLongLong(value) => (i128::from(value), IntegerKind::LongLong, false),
+        };
+        IntegerConstant {
+            value,
+            ty: IntegerType { kind, signed },
+        }
+    }
+}
+
+impl<'ctx> IR<'ctx> {
+    /// The value of an integer constant.
+    pub(super) fn constant_value(&self, constant: IntegerConstant) -> Value<'ctx> {
+        let value = self
+            .context
+            .custom_width_int_type(constant.ty.bits())
+            .const_int(constant.value as u64, false);
+        Value::int(value, constant.ty)
+    }
+
+    /// Value of an integer constant expression, e.g. a `case` label, with
+    /// its type after the usual arithmetic conversions.
+    pub(super) fn constant_int(&mut self, expr: &Expression) -> anyhow::Result<IntegerConstant> {
+        let not_constant = || SemaError::NotConstant { span: expr.span() };
+        let constant = match expr {
+            Expression::Constant(constant, _) => IntegerConstant::from(constant),
+            Expression::Paren(expr, _) | Expression::Evaluate(expr) => self.constant_int(expr)?,
+            Expression::RefOrCall(RefOrCall::Variable(name, _)) => {
+                self.enum_constant(name).ok_or_else(not_constant)?.value
+            }
+            // only casts to integer types, as in `(int)sizeof(long)`
+            Expression::Cast(ty, operand, span) => {
+                let Type::Integer(ty) = self.resolve_type(ty.clone(), *span)? else {
+                    anyhow::bail!(not_constant());
+                };
+                IntegerConstant::new(self.constant_int(operand)?.value, ty)
+            }
+            Expression::SizeofType(ty, span) => {
+                let ty = self.resolve_type(ty.clone(), *span)?;
+                let size = self.size_of(ty, *span)?;
+                IntegerConstant::new(i128::from(size), SIZE_T)
+            }
+            Expression::Sizeof(operand, span) => {
+                let ty = self.type_of(operand)?;
+                let size = self.size_of(ty, span.to(operand.span()))?;
+                IntegerConstant::new(i128::from(size), SIZE_T)
+            }
+            Expression::PrefixUnary(op, operand, _) => {
+                let operand = self.constant_int(operand)?;
+                let ty = promoted(operand.ty);
+                let value = operand.value;
+                match op {
+                    PrefixUnaryOperator::Plus => IntegerConstant::new(value, ty),
+                    PrefixUnaryOperator::Minus => {
+                        arithmetic(value.wrapping_neg(), ty).ok_or_else(not_constant)?
+                    }
+                    PrefixUnaryOperator::Not => truth(value == 0),
+                    PrefixUnaryOperator::BitNot => IntegerConstant::new(!value, ty),
+                    _ => anyhow::bail!(not_constant()),
+                }
+            }
+            Expression::Conditional(condition, then, otherwise, _) => {
+                let (selected, other) = if self.constant_int(condition)?.value != 0 {
+                    (then, otherwise)
+                } else {
+                    (otherwise, then)
+                };
+                let selected = self.constant_int(selected)?;
+                // the other branch is not evaluated, e.g. it may divide by zero
+                let ty = match self.constant_int(other) {
+                    Ok(other) => common_type(selected.ty, other.ty),
+                    Err(_) => promoted(selected.ty),
+                };
+                IntegerConstant::new(selected.value, ty)
+            }
+            Expression::Binary(lhs, BinaryOperator::LogicalAnd, rhs, _) => {
+                truth(self.constant_int(lhs)?.value != 0 && self.constant_int(rhs)?.value != 0)
+            }
+            Expression::Binary(lhs, BinaryOperator::LogicalOr, rhs, _) => {
+                truth(self.constant_int(lhs)?.value != 0 || self.constant_int(rhs)?.value != 0)
+            }
+            Expression::Binary(
+                lhs,
+                op @ (BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight),
+                rhs,
+                _,
+            ) => {
+                let lhs = self.constant_int(lhs)?;
+                let rhs = self.constant_int(rhs)?;
+                let ty = promoted(lhs.ty);
+                // shifting by a negative count or the width or more is
+                // undefined, as is shifting a negative value left
+                let count = u32::try_from(rhs.value)
+                    .ok()
+                    .filter(|count| *count < ty.bits())
+                    .ok_or_else(not_constant)?;
+                match op {
+                    BinaryOperator::ShiftLeft if lhs.value < 0 => anyhow::bail!(not_constant()),
+                    BinaryOperator::ShiftLeft => {
+                        arithmetic(lhs.value << count, ty).ok_or_else(not_constant)?
+                    }
+                    _ => IntegerConstant::new(lhs.value >> count, ty),
+                }
+            }
+            Expression::Binary(lhs, bin_op, rhs, _) => {
+                let lhs = self.constant_int(lhs)?;
+                let rhs = self.constant_int(rhs)?;
+                let ty = common_type(lhs.ty, rhs.ty);
+                let lhs = IntegerConstant::new(lhs.value, ty).value;
+                let rhs = IntegerConstant::new(rhs.value, ty).value;
+                // overflow and division by zero are undefined, so not constant;
+                // the operations wrap at 128 bits, as the product of two
+                // `unsigned long long` values may not fit, which still leaves
+                // the value modulo 2 to the width of `ty`
+                let value = match bin_op {
+                    BinaryOperator::Plus => arithmetic(lhs.wrapping_add(rhs), ty),
+                    BinaryOperator::Minus => arithmetic(lhs.wrapping_sub(rhs), ty),
+                    BinaryOperator::Multiply => arithmetic(lhs.wrapping_mul(rhs), ty),
+                    BinaryOperator::Divide => {
+                        lhs.checked_div(rhs).and_then(|value| arithmetic(value, ty))
+                    }
+                    BinaryOperator::Modulo => {
+                        lhs.checked_rem(rhs).and_then(|value| arithmetic(value, ty))
+                    }
+                    BinaryOperator::Less => Some(truth(lhs < rhs)),
+                    BinaryOperator::LessEqual => Some(truth(lhs <= rhs)),
+                    BinaryOperator::Greater => Some(truth(lhs > rhs)),
+                    BinaryOperator::GreaterEqual => Some(truth(lhs >= rhs)),
+                    BinaryOperator::Equal => Some(truth(lhs == rhs)),
+                    BinaryOperator::NotEqual => Some(truth(lhs != rhs)),
+                    BinaryOperator::BitAnd => Some(IntegerConstant::new(lhs & rhs, ty)),
+                    BinaryOperator::BitXor => Some(IntegerConstant::new(lhs ^ rhs, ty)),
+                    BinaryOperator::BitOr => Some(IntegerConstant::new(lhs | rhs, ty)),
+                    _ => None,
+                };
+                value.ok_or_else(not_constant)?
+            }
+            _ => anyhow::bail!(not_constant()),
+        };
+        Ok(constant)
+    }
+
+    /// Whether `expr` is a null pointer constant: an integer constant
+    /// expression with the value 0, or one cast to `void *`.
+    pub(super) fn is_null_pointer_constant(&mut self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Paren(expr, _) | Expression::Evaluate(expr) => {
+                self.is_null_pointer_constant(expr)
+            }
+            Expression::Cast(ty, operand, span) => match self.resolve_type(ty.clone(), *span) {
+                Ok(Type::Pointer(pointee)) if *pointee == Type::Void => {
+                    self.is_null_pointer_constant(operand)
+                }
+                _ => matches!(self.constant_int(expr), Ok(constant) if constant.value == 0),
+            },
+            expr => matches!(self.constant_int(expr), Ok(constant) if constant.value == 0),
+        }
+    }
+}
+
+/// The `int` result of a comparison or logical operator.
+fn truth(value: bool) -> IntegerConstant {
+    IntegerConstant::new(i128::from(value), IntegerType::INT)
+}
+
+/// The result of an arithmetic operator of type `ty`, unless it overflows a
+/// signed type. Unsigned arithmetic wraps around.
+fn arithmetic(value: i128, ty: IntegerType) -> Option<IntegerConstant> {
+    let constant = IntegerConstant::new(value, ty);
+    (!ty.signed || constant.value == value).then_some(constant)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{has_lines, lower};
+
+    #[test]
+    fn unsigned_long_long_products_wrap() {
+        let ir = lower(
+            "enum e { E = 0xFFFFFFFFFFFFFFFFull * 0xFFFFFFFFFFFFFFFFull };\n\
+             int f(unsigned long long x) {\n\
+                 char a[0xFFFFFFFFFFFFFFFFull * 0xFFFFFFFFFFFFFFFFull + E];\n\
+                 switch (x) { case 0xFFFFFFFFFFFFFFFFull * 0xFFFFFFFFFFFFFFFFull: return 1; }\n\
+                 return 0;\n\
+             }",
+        )
+        .unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "%a = alloca [2 x i8]",
+                    "switch i64 %load, label %switch_end [",
+                    "i64 1, label %switch_case"
+                ]
+            ),
+            "{ir}"
+        );
+    }
+}
@@ -1,54 +1,72 @@
-use inkwell::{types::BasicType, values::BasicValueEnum};
+use inkwell::{basic_block::BasicBlock, types::BasicTypeEnum, values::PointerValue};
 
 use crate::{
+    diagnostics::{Diagnostic, Span},
     semantic_analysis::remove_dead_code,
     syntax_analysis::{
-        declaration::Declaration, expression::Expression,
-        parameter_definition::ParameterDefinition, types::Type,
+        declaration::Declaration, parameter_definition::ParameterDefinition, types::Type,
     },
 };
 
-use super::IR;
+use super::{SemaError, Signature, Variable, IR};
 
 impl<'ctx> IR<'ctx> {
     pub(super) fn analysis_declaration(&mut self, decl: Declaration) -> anyhow::Result<()> {
         match decl {
-            Declaration::Function(ret_type, name, params, body) => {
-                let param_types: Vec<_> = params
-                    .iter()
-                    .map(|ParameterDefinition(param_type, _)| match param_type {
-                        Type::Int => self.context.i32_type().as_basic_type_enum(),
-                        Type::Void => unreachable!(),
-                    })
-                    .collect();
-                let fn_type = {
-                    let param_types: Box<[_]> =
-                        param_types.iter().map(|t| t.to_owned().into()).collect();
-                    match ret_type {
-                        Type::Int => self.context.i32_type().fn_type(&param_types, false),
-                        Type::Void => self.context.void_type().fn_type(&param_types, false),
+            Declaration::Function(ret_type, name, params, body, span) => {
+                let return_type = self.resolve_return_type(ret_type, span)?;
+                let mut param_types = Vec::new();
+                for ParameterDefinition(param_type, param_name, param_span) in &params {
+                    let param_type = self.resolve_parameter(param_type.clone(), *param_span)?;
+                    if body.is_some() && !self.is_complete(&param_type) {
+                        anyhow::bail!(SemaError::IncompleteType {
+                            name: param_name.clone().unwrap_or_default(),
+                            span: *param_span,
+                        });
                     }
+                    param_types.push(param_type);
+                }
+                let signature = Signature {
+                    return_type,
+                    params: param_types,
                 };
+                let fn_type = self.fn_type(&signature);
                 if self.builder.get_insert_block().is_some() {
-                    anyhow::bail!("Local function in not implemented");
+                    anyhow::bail!(SemaError::NestedFunction { span });
                 } else {
                     let func = match self.module.get_function(&name) {
                         None => self.module.add_function(&name, fn_type, None),
                         Some(prev_def) => {
-                            anyhow::ensure!(
-                                prev_def.get_type() == fn_type,
-                                "Function {name} redefined."
-                            );
-                            anyhow::ensure!(
-                                prev_def.get_basic_blocks().is_empty(),
-                                "Function {name} redefined."
-                            );
+                            let (Some(previous), Some(&prev_span)) =
+                                (self.functions.get(&name), self.function_spans.get(&name))
+                            else {
+                                anyhow::bail!("function `{name}` has no signature");
+                            };
+                            if *previous != signature {
+                                anyhow::bail!(SemaError::ConflictingTypes {
+                                    name,
+                                    span,
+                                    previous: prev_span,
+                                });
+                            }
+                            if body.is_some() && !prev_def.get_basic_blocks().is_empty() {
+                                anyhow::bail!(SemaError::Redefinition {
+                                    name,
+                                    span,
+                                    previous: Some(prev_span),
+                                });
+                            }
                             prev_def
                         }
                     };
+                    self.functions.insert(name.to_owned(), signature.clone());
+                    if body.is_some() || !self.function_spans.contains_key(&name) {
+                        self.function_spans.insert(name.to_owned(), span);
+                    }
                     if let Some(body) = body {
                         let entry_block = self.context.append_basic_block(func, "entry");
                         let return_block = self.context.append_basic_block(func, "return");
+                        self.return_type = signature.return_type.clone();
                         match fn_type.get_return_type() {
                             None => {
                                 self.return_value = None;
@@ -57,9 +75,7 @@ impl<'ctx> IR<'ctx> {
                             }
                             Some(ret_type) => {
                                 self.builder.position_at_end(entry_block);
-                                let alloca = self
-                                    .builder
-                                    .build_alloca(self.context.i32_type(), "return_value")?;
+                                let alloca = self.builder.build_alloca(ret_type, "return_value")?;
                                 self.return_value = Some(alloca);
                                 self.builder.position_at_end(return_block);
                                 let return_value = self.builder.build_load(
@@ -77,65 +93,275 @@ impl<'ctx> IR<'ctx> {
                             "length of parameter list changed"
                         );
                         self.local_variables.clear();
+                        self.local_enumerators.clear();
+                        self.local_typedefs.clear();
+                        self.scope_variables.clear();
+                        self.local_tags.clear();
+                        self.scope_tags.clear();
+                        self.labels.clear();
                         for (i, arg) in args.into_iter().enumerate() {
-                            let ParameterDefinition(_, param_name) = &params[i];
+                            // an unnamed parameter cannot be used
+                            let ParameterDefinition(_, Some(param_name), param_span) = &params[i]
+                            else {
+                                continue;
+                            };
                             let alloca = self.builder.build_alloca(arg.get_type(), param_name)?;
-                            let None = self
-                                .local_variables
-                                .insert(param_name.to_owned(), alloca)
+                            let variable = Variable {
+                                pointer: alloca,
+                                ty: signature.params[i].clone(),
+                                span: *param_span,
+                            };
+                            let None = self.local_variables.insert(param_name.to_owned(), variable)
                             else {
-                                anyhow::bail!("Duplicate parameter name: {param_name}");
+                                anyhow::bail!(SemaError::DuplicateParameter {
+                                    name: param_name.to_owned(),
+                                    span: *param_span,
+                                });
                             };
+                            self.scope_variables.insert(param_name.to_owned());
                             self.builder.build_store(alloca, arg)?;
                         }
                         self.analysis_block(body)?;
+                        let undefined_label = self
+                            .labels
+                            .drain()
+                            .filter_map(|(name, label)| match label.definition {
+                                None => Some((name, label.first_use?)),
+                                Some(_) => None,
+                            })
+                            .min_by_key(|(_, span)| span.start);
+                        if let Some((name, span)) = undefined_label {
+                            anyhow::bail!(SemaError::UndefinedLabel { name, span });
+                        }
                         for bb in func.get_basic_block_iter() {
                             remove_dead_code(&bb);
                         }
+                        let last_block = self.builder.get_insert_block().unwrap();
+                        if last_block.get_terminator().is_none() {
+                            let reachable =
+                                last_block == entry_block || last_block.get_first_use().is_some();
+                            if reachable && fn_type.get_return_type().is_some() {
+                                self.diagnostics.push(
+                                    Diagnostic::warning(format!(
+                                        "non-void function `{name}` does not return a value in all control paths"
+                                    ))
+                                    .with_primary(span, "function defined here")
+                                    .with_note("the value returned by falling off the end is undefined"),
+                                );
+                            }
+                            self.builder.build_unconditional_branch(return_block)?;
+                        }
                         anyhow::ensure!(func.verify(cfg!(debug_assertions)), "Illegal function");
                         self.builder.clear_insertion_position();
+                        // the locals of the function go out of scope
+                        self.local_variables.clear();
+                        self.local_enumerators.clear();
+                        self.local_typedefs.clear();
+                        self.local_tags.clear();
                     }
                 }
             }
-            Declaration::Variable(var_type, name, value) => {
-                let var_type = match var_type {
-                    Type::Int => self.context.i32_type().as_basic_type_enum(),
-                    Type::Void => anyhow::bail!("Variable cannot be of void type"),
-                };
-                if self.local_variables.contains_key(&name) {
-                    anyhow::bail!("Redifined {name}");
+            Declaration::Variable(var_type, name, value, span) => {
+                let var_type = self.resolve_type(var_type, span)?;
+                if var_type == Type::Void {
+                    anyhow::bail!(SemaError::VoidVariable { name, span });
+                }
+                // a function declared with a typedef name, as in `F f;`
+                if let Type::Function(return_type, param_types) = var_type {
+                    if value.is_some() {
+                        anyhow::bail!(SemaError::FunctionInitialized { name, span });
+                    }
+                    let params = param_types
+                        .into_iter()
+                        .map(|param_type| ParameterDefinition(param_type, None, span))
+                        .collect();
+                    let decl = Declaration::Function(*return_type, name, params, None, span);
+                    return self.analysis_declaration(decl);
                 }
                 if let Some(bb) = self.builder.get_insert_block() {
-                    let this_func = bb.get_parent().unwrap();
-                    let entry = this_func.get_first_basic_block().unwrap();
-                    let alloca = {
-                        let builder = self.context.create_builder();
-                        builder.position_at_end(entry);
-                        builder.build_alloca(var_type, &name)?
+                    let (var_type, value) = match value {
+                        Some(value) => {
+                            let (var_type, scalars) =
+                                self.designate_initializer(var_type, value)?;
+                            (var_type, Some(scalars))
+                        }
+                        None => (var_type, None),
                     };
-                    self.local_variables.insert(name, alloca);
-                    if let Some(value) = value {
-                        let Some(value) = self.analysis_expression(value)? else {
-                            anyhow::bail!("Operand cannot be void")
-                        };
-                        match value {
-                            BasicValueEnum::IntValue(value) => {
-                                self.builder.build_store(alloca, value)?;
+                    if !self.is_complete(&var_type) {
+                        anyhow::bail!(SemaError::IncompleteType { name, span });
+                    }
+                    let llvm_type = self.llvm_type(&var_type).unwrap();
+                    if !self.scope_variables.insert(name.to_owned()) {
+                        anyhow::bail!(SemaError::Redefinition {
+                            name,
+                            span,
+                            previous: None,
+                        });
+                    }
+                    let alloca = self.build_entry_alloca(bb, llvm_type, &name)?;
+                    self.local_enumerators.remove(&name);
+                    let variable = Variable {
+                        pointer: alloca,
+                        ty: var_type.clone(),
+                        span,
+                    };
+                    self.local_variables.insert(name, variable);
+                    if let Some(scalars) = value {
+                        self.initialize(alloca, &var_type, scalars)?;
+                    }
+                } else {
+                    let other_declaration = match self.global_enumerators.get(&name) {
+                        Some(constant) => Some(Some(constant.span)),
+                        None => self.global_typedefs.contains_key(&name).then_some(None),
+                    };
+                    if let Some(previous) = other_declaration {
+                        anyhow::bail!(SemaError::Redefinition {
+                            name,
+                            span,
+                            previous,
+                        });
+                    }
+                    let (var_type, value) = match value {
+                        Some(value) => {
+                            let span = value.span();
+                            let (var_type, scalars) =
+                                self.designate_initializer(var_type, value)?;
+                            if !self.is_complete(&var_type) {
+                                anyhow::bail!(SemaError::IncompleteType { name, span });
                             }
-                            _ => anyhow::bail!("Unimplemented type of operand"),
+                            (var_type, Some((scalars, span)))
+                        }
+                        None => (var_type, None),
+                    };
+                    match self.global_variables.get(&name) {
+                        // a redeclaration of the same object
+                        Some(previous) if previous.ty == var_type => {}
+                        Some(previous) => anyhow::bail!(SemaError::ConflictingTypes {
+                            name,
+                            span,
+                            previous: previous.span,
+                        }),
+                        None => {
+                            if let Type::Record(_) = var_type {
+                                if !self.is_complete(&var_type) {
+                                    anyhow::bail!(SemaError::IncompleteType { name, span });
+                                }
+                            }
+                            let llvm_type = self.llvm_type(&var_type).unwrap();
+                            let global = self.module.add_global(llvm_type, None, &name);
+                            let variable = Variable {
+                                pointer: global.as_pointer_value(),
+                                ty: var_type.clone(),
+                                span,
+                            };
+                            self.global_variables.insert(name.to_owned(), variable);
                         }
                     }
-                } else {
-                    self.module.add_global(var_type, None, &name);
-                    if let Some(value) = value {
-                        let Expression::Constant(_value) = value else {
-                            anyhow::bail!("Global variable can only be initialized with constant");
+                    // without an initializer, this is a tentative definition,
+                    // which is zero-initialized at the end of the translation
+                    // unit unless a later declaration initializes the object
+                    if let Some((scalars, value_span)) = value {
+                        let Some(global) = self.module.get_global(&name) else {
+                            anyhow::bail!("global variable `{name}` is not in the module");
                         };
-                        anyhow::bail!("Global variable initialization unimplemented");
+                        if global.get_initializer().is_some() {
+                            let previous = self.global_variables.get(&name).map(|var| var.span);
+                            anyhow::bail!(SemaError::Redefinition {
+                                name,
+                                span,
+                                previous,
+                            });
+                        }
+                        let initial_value =
+                            self.constant_initializer(&var_type, scalars, value_span)?;
+                        global.set_initializer(&initial_value);
                     }
                 }
             }
+            Declaration::Tag(Type::RecordSpecifier(specifier), _) => {
+                self.resolve_record(*specifier, true)?;
+            }
+            Declaration::Tag(Type::EnumSpecifier(specifier), _) => {
+                self.resolve_enum(*specifier)?;
+            }
+            Declaration::Tag(ty, span) => {
+                anyhow::bail!("Tag declaration of `{ty}` at {span:?} is not a specifier")
+            }
+            Declaration::Typedef(ty, name, span) => {
+                let ty = self.resolve_type(ty, span)?;
+                self.declare_typedef(name, ty, span)?;
+            }
+            Declaration::Error(span) => {
+                anyhow::bail!("Syntax error node at {span:?} reached semantic analysis")
+            }
+        }
+        Ok(())
+    }
+
+    /// Declare `name` as a typedef name for `ty` in the current scope. It may
+    /// be declared again there for the same type.
+    fn declare_typedef(&mut self, name: String, ty: Type, span: Span) -> anyhow::Result<()> {
+        if self.builder.get_insert_block().is_some() {
+            let same = self.local_typedefs.get(&name) == Some(&ty);
+            if !self.scope_variables.insert(name.to_owned()) && !same {
+                anyhow::bail!(SemaError::Redefinition {
+                    name,
+                    span,
+                    previous: None,
+                });
+            }
+            self.local_typedefs.insert(name, ty);
+        } else {
+            if let Some(previous) = self.global_typedefs.get(&name) {
+                if *previous != ty {
+                    anyhow::bail!(SemaError::Redefinition {
+                        name,
+                        span,
+                        previous: None,
+                    });
+                }
+            }
+            let previous = self
+                .global_variables
+                .get(&name)
+                .map(|variable| variable.span)
+                .or_else(|| self.global_enumerators.get(&name).map(|c| c.span))
+                .or_else(|| self.function_spans.get(&name).copied());
+            if previous.is_some() {
+                anyhow::bail!(SemaError::Redefinition {
+                    name,
+                    span,
+                    previous,
+                });
+            }
+            self.global_typedefs.insert(name, ty);
         }
         Ok(())
     }
+
+    /// The type named by the typedef name `name`.
+    pub(super) fn typedef_type(&self, name: &str) -> Option<Type> {
+        self.local_typedefs
+            .get(name)
+            .or_else(|| self.global_typedefs.get(name))
+            .cloned()
+    }
+
+    /// Allocate a local object in the entry block of the function containing
+    /// `bb`, so that it is allocated once however often its declaration runs.
+    pub(super) fn build_entry_alloca(
+        &self,
+        bb: BasicBlock<'ctx>,
+        llvm_type: BasicTypeEnum<'ctx>,
+        name: &str,
+    ) -> anyhow::Result<PointerValue<'ctx>> {
+        let entry = bb.get_parent().unwrap().get_first_basic_block().unwrap();
+        // the entry block may already be terminated by a branch
+        let builder = self.context.create_builder();
+        match entry.get_terminator() {
+            Some(terminator) => builder.position_before(&terminator),
+            None => builder.position_at_end(entry),
+        }
+        Ok(builder.build_alloca(llvm_type, name)?)
+    }
 }
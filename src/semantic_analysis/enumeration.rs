@@ -0,0 +1,181 @@
+use crate::{
+    diagnostics::Span,
+    syntax_analysis::types::{EnumSpecifier, Enumerator, IntegerType, Type},
+};
+
+use super::{constant::IntegerConstant, types::Value, SemaError, Tag, IR};
+
+/// An enumeration constant in scope, with the type of the enumerators of
+/// its enumeration.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct EnumConstant {
+    pub(super) value: IntegerConstant,
+    pub(super) span: Span,
+}
+
+impl<'ctx> IR<'ctx> {
+    /// Resolve an enumeration specifier to its underlying type, declaring
+    /// its enumerators if it lists them. The value of each enumerator must be
+    /// representable in the fixed underlying type, if any, or else in `int`.
+    /// Without a fixed underlying type, an enumeration is `unsigned int`
+    /// unless it has negative values, as in GCC, and its enumerators are
+    /// `int`s.
+    pub(super) fn resolve_enum(&mut self, specifier: EnumSpecifier) -> anyhow::Result<IntegerType> {
+        let EnumSpecifier {
+            tag,
+            underlying,
+            enumerators,
+            span,
+        } = specifier;
+        let underlying = match underlying {
+            None => None,
+            Some(ty) => match self.resolve_type(ty, span)? {
+                Type::Integer(ty) => Some(ty),
+                ty => anyhow::bail!(SemaError::InvalidUnderlyingType { ty, span }),
+            },
+        };
+        // only a plain reference like `enum e x;` refers to an outer scope
+        let visible = underlying.is_none() && enumerators.is_none();
+        let existing = tag.as_deref().and_then(|tag| self.find_tag(tag, visible));
+        match existing {
+            Some(Tag::Record(id)) => anyhow::bail!(SemaError::TagKindMismatch {
+                tag: tag.unwrap(),
+                span,
+                previous: self.record_span(id),
+            }),
+            Some(Tag::Enum {
+                underlying: previous_type,
+                fixed,
+                defined,
+                span: previous,
+            }) => {
+                let name = format!("enum {}", tag.as_deref().unwrap());
+                if !visible && underlying != fixed.then_some(previous_type) {
+                    anyhow::bail!(SemaError::ConflictingTypes {
+                        name,
+                        span,
+                        previous,
+                    });
+                }
+                if enumerators.is_none() {
+                    return Ok(previous_type);
+                }
+                if defined {
+                    anyhow::bail!(SemaError::Redefinition {
+                        name,
+                        span,
+                        previous: Some(previous),
+                    });
+                }
+            }
+            None if enumerators.is_none() => match underlying {
+                None => anyhow::bail!(SemaError::UndeclaredEnum {
+                    tag: tag.unwrap(),
+                    span,
+                }),
+                // `enum e : long;` declares an enumeration to be defined later
+                Some(underlying) => {
+                    let declaration = Tag::Enum {
+                        underlying,
+                        fixed: true,
+                        defined: false,
+                        span,
+                    };
+                    self.insert_tag(tag.unwrap(), declaration);
+                    return Ok(underlying);
+                }
+            },
+            None => {}
+        }
+        let fixed = underlying.is_some();
+        let enumerator_type = underlying.unwrap_or(IntegerType::INT);
+        let mut next = 0;
+        let mut negative = false;
+        for Enumerator(name, value, span) in enumerators.unwrap() {
+            let value = match value {
+                Some(value) => self.constant_int(&value)?.value,
+                None => next,
+            };
+            let constant = IntegerConstant::new(value, enumerator_type);
+            if constant.value != value {
+                anyhow::bail!(SemaError::EnumeratorRange {
+                    name,
+                    ty: Type::Integer(enumerator_type),
+                    span,
+                });
+            }
+            negative |= value < 0;
+            next = value + 1;
+            let constant = EnumConstant {
+                value: constant,
+                span,
+            };
+            self.declare_enumerator(name, constant)?;
+        }
+        let underlying = underlying.unwrap_or(IntegerType {
+            signed: negative,
+            ..IntegerType::INT
+        });
+        if let Some(tag) = tag {
+            let declaration = Tag::Enum {
+                underlying,
+                fixed,
+                defined: true,
+                span,
+            };
+            self.insert_tag(tag, declaration);
+        }
+        Ok(underlying)
+    }
+
+    /// Declare an enumeration constant in the current scope, where it may
+    /// shadow a variable of an enclosing one.
+    fn declare_enumerator(&mut self, name: String, constant: EnumConstant) -> anyhow::Result<()> {
+        if self.builder.get_insert_block().is_some() {
+            if !self.scope_variables.insert(name.to_owned()) {
+                anyhow::bail!(SemaError::Redefinition {
+                    name,
+                    span: constant.span,
+                    previous: None,
+                });
+            }
+            self.local_variables.remove(&name);
+            self.local_enumerators.insert(name, constant);
+        } else {
+            let previous = self
+                .global_variables
+                .get(&name)
+                .map(|variable| variable.span)
+                .or_else(|| self.global_enumerators.get(&name).map(|c| c.span))
+                .or_else(|| self.function_spans.get(&name).copied());
+            if previous.is_some() {
+                anyhow::bail!(SemaError::Redefinition {
+                    name,
+                    span: constant.span,
+                    previous,
+                });
+            }
+            self.global_enumerators.insert(name, constant);
+        }
+        Ok(())
+    }
+
+    /// The enumeration constant `name` refers to, unless it refers to a
+    /// variable or nothing.
+    pub(super) fn enum_constant(&self, name: &str) -> Option<EnumConstant> {
+        if self.local_variables.contains_key(name) {
+            None
+        } else if let Some(constant) = self.local_enumerators.get(name) {
+            Some(*constant)
+        } else if self.global_variables.contains_key(name) {
+            None
+        } else {
+            self.global_enumerators.get(name).copied()
+        }
+    }
+
+    /// Value of an enumeration constant.
+    pub(super) fn enum_value(&self, constant: EnumConstant) -> Value<'ctx> {
+        self.constant_value(constant.value)
+    }
+}
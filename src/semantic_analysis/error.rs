@@ -0,0 +1,420 @@
+use crate::{
+    diagnostics::{Diagnostic, Span},
+    syntax_analysis::types::Type,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SemaError {
+    #[error("cannot find variable `{name}` in this scope")]
+    UndefinedVariable { name: String, span: Span },
+    #[error("cannot find function `{name}` in this scope")]
+    UndefinedFunction { name: String, span: Span },
+    #[error(
+        "{} takes {expected} argument{} but {found} argument{} supplied",
+        callee(.name),
+        plural(.expected),
+        if *.found == 1 { " was" } else { "s were" }
+    )]
+    ArgumentCount {
+        /// Name of the function, unless it is called through a pointer.
+        name: Option<String>,
+        expected: usize,
+        found: usize,
+        span: Span,
+        /// Where the function was declared, if known.
+        declaration: Option<Span>,
+    },
+    /// A `void` expression used where a value is needed, e.g. as `what`
+    /// "operand" of the operator at `operator`.
+    #[error("{what} cannot be `void`")]
+    VoidValue {
+        what: &'static str,
+        span: Span,
+        operator: Option<Span>,
+    },
+    /// The operand of an assignment, `++` or `--` is not an lvalue.
+    #[error("expression is not assignable")]
+    InvalidAssignment { span: Span, operator: Span },
+    #[error("redefinition of `{name}`")]
+    Redefinition {
+        name: String,
+        span: Span,
+        previous: Option<Span>,
+    },
+    #[error("conflicting types for `{name}`")]
+    ConflictingTypes {
+        name: String,
+        span: Span,
+        previous: Span,
+    },
+    #[error("mismatched return type")]
+    ReturnTypeMismatch { span: Span },
+    #[error("non-void function should return a value")]
+    MissingReturnValue { span: Span },
+    #[error("void function should not return a value")]
+    UnexpectedReturnValue { span: Span },
+    #[error("variable `{name}` declared `void`")]
+    VoidVariable { name: String, span: Span },
+    #[error("identifier `{name}` is bound more than once in the parameter list")]
+    DuplicateParameter { name: String, span: Span },
+    #[error("function declarations inside a block are not supported")]
+    NestedFunction { span: Span },
+    #[error("incompatible operand types in conditional expression")]
+    ConditionalTypeMismatch {
+        then_span: Span,
+        otherwise_span: Span,
+        operator: Span,
+    },
+    #[error("called object of type `{ty}` is not a function or function pointer")]
+    NotCallable { ty: Type, span: Span },
+    #[error("subscripted value is not an array or pointer")]
+    NotSubscriptable { span: Span, operator: Span },
+    #[error("request for member `{member}` in something not a structure or union")]
+    NotAStructure { member: String, span: Span },
+    #[error("`break` statement not within a loop or switch")]
+    BreakOutsideLoop { span: Span },
+    #[error("`continue` statement not within a loop")]
+    ContinueOutsideLoop { span: Span },
+    #[error("expression is not an integer constant expression")]
+    NotConstant { span: Span },
+    #[error("duplicate case value `{value}`")]
+    DuplicateCase {
+        value: i128,
+        span: Span,
+        previous: Span,
+    },
+    #[error("multiple default labels in one switch")]
+    MultipleDefault { span: Span, previous: Span },
+    #[error("`{label}` label not within a switch statement")]
+    LabelOutsideSwitch { label: &'static str, span: Span },
+    #[error("use of undeclared label `{name}`")]
+    UndefinedLabel { name: String, span: Span },
+    #[error("redefinition of label `{name}`")]
+    DuplicateLabel {
+        name: String,
+        span: Span,
+        previous: Span,
+    },
+    /// A value that cannot be implicitly converted for `what`.
+    #[error("incompatible type in {what}")]
+    IncompatibleType { what: &'static str, span: Span },
+    #[error("invalid operands to binary operator (`{lhs}` and `{rhs}`)")]
+    InvalidOperands { lhs: Type, rhs: Type, span: Span },
+    #[error("invalid operand of type `{ty}` to unary operator")]
+    InvalidUnaryOperand {
+        ty: Type,
+        span: Span,
+        operator: Span,
+    },
+    #[error("cannot take the address of an rvalue")]
+    AddressOfRvalue { span: Span, operator: Span },
+    #[error("size of array is not positive")]
+    InvalidArrayLength { span: Span },
+    #[error("array has incomplete element type `{ty}`")]
+    IncompleteElementType { ty: Type, span: Span },
+    #[error("storage size of `{name}` is not known")]
+    IncompleteType { name: String, span: Span },
+    #[error("`{tag}` defined as the wrong kind of tag")]
+    TagKindMismatch {
+        tag: String,
+        span: Span,
+        previous: Span,
+    },
+    #[error("duplicate member `{name}`")]
+    DuplicateMember {
+        name: String,
+        span: Span,
+        previous: Span,
+    },
+    #[error("no member named `{member}` in `{ty}`")]
+    NoSuchMember {
+        member: String,
+        ty: Type,
+        span: Span,
+    },
+    #[error("member access into incomplete type `{ty}`")]
+    IncompleteRecord { ty: Type, span: Span },
+    #[error("used type `{ty}` where a scalar is required")]
+    NotScalar { ty: Type, span: Span },
+    #[error("value of enumerator `{name}` is not representable in `{ty}`")]
+    EnumeratorRange { name: String, ty: Type, span: Span },
+    #[error("use of `enum {tag}` without a previous declaration")]
+    UndeclaredEnum { tag: String, span: Span },
+    #[error("cannot cast `{from}` to `{to}`")]
+    InvalidCast { from: Type, to: Type, span: Span },
+    #[error("underlying type `{ty}` of an enumeration is not an integer type")]
+    InvalidUnderlyingType { ty: Type, span: Span },
+    #[error("invalid application of `sizeof` to `{ty}`")]
+    InvalidSizeof { ty: Type, span: Span },
+    #[error("function cannot return `{ty}`")]
+    InvalidReturnType { ty: Type, span: Span },
+    #[error("function `{name}` is initialized like a variable")]
+    FunctionInitialized { name: String, span: Span },
+    #[error("arithmetic on a pointer to `{ty}`, whose size is not known")]
+    IncompletePointee { ty: Type, span: Span },
+    #[error("initializer element is not a compile-time constant")]
+    NonConstantInitializer { span: Span },
+    #[error("switch quantity of type `{ty}` is not an integer")]
+    NonIntegerSwitch { ty: Type, span: Span },
+    #[error("{feature} is not supported yet")]
+    Unsupported { feature: &'static str, span: Span },
+    /// A bug in the compiler rather than in the compiled code.
+    #[error("internal compiler error: {message}")]
+    Internal {
+        message: String,
+        /// Whether the LLVM module was printed for debugging.
+        dumped: bool,
+    },
+}
+
+/// What the callee of a call with the wrong number of arguments is called.
+fn callee(name: &Option<String>) -> String {
+    match name {
+        Some(name) => format!("function `{name}`"),
+        None => "called function".to_owned(),
+    }
+}
+
+fn plural(n: &usize) -> &'static str {
+    if *n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+impl SemaError {
+    /// Stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemaError::Internal { .. } => "E0200",
+            SemaError::UndefinedVariable { .. } => "E0201",
+            SemaError::UndefinedFunction { .. } => "E0202",
+            SemaError::ArgumentCount { .. } => "E0203",
+            SemaError::VoidValue { .. } => "E0204",
+            SemaError::InvalidAssignment { .. } => "E0205",
+            SemaError::Redefinition { .. } => "E0206",
+            SemaError::ConflictingTypes { .. } => "E0207",
+            SemaError::ReturnTypeMismatch { .. } => "E0208",
+            SemaError::MissingReturnValue { .. } => "E0209",
+            SemaError::UnexpectedReturnValue { .. } => "E0210",
+            SemaError::VoidVariable { .. } => "E0211",
+            SemaError::DuplicateParameter { .. } => "E0212",
+            SemaError::NestedFunction { .. } => "E0213",
+            SemaError::Unsupported { .. } => "E0214",
+            SemaError::ConditionalTypeMismatch { .. } => "E0215",
+            SemaError::NotCallable { .. } => "E0216",
+            SemaError::NotSubscriptable { .. } => "E0217",
+            SemaError::NotAStructure { .. } => "E0218",
+            SemaError::BreakOutsideLoop { .. } => "E0219",
+            SemaError::ContinueOutsideLoop { .. } => "E0220",
+            SemaError::NotConstant { .. } => "E0221",
+            SemaError::DuplicateCase { .. } => "E0222",
+            SemaError::MultipleDefault { .. } => "E0223",
+            SemaError::LabelOutsideSwitch { .. } => "E0224",
+            SemaError::UndefinedLabel { .. } => "E0225",
+            SemaError::DuplicateLabel { .. } => "E0226",
+            SemaError::IncompatibleType { .. } => "E0227",
+            SemaError::InvalidOperands { .. } => "E0228",
+            SemaError::InvalidUnaryOperand { .. } => "E0229",
+            SemaError::AddressOfRvalue { .. } => "E0230",
+            SemaError::InvalidArrayLength { .. } => "E0231",
+            SemaError::IncompleteElementType { .. } => "E0232",
+            SemaError::IncompleteType { .. } => "E0233",
+            SemaError::TagKindMismatch { .. } => "E0234",
+            SemaError::DuplicateMember { .. } => "E0235",
+            SemaError::NoSuchMember { .. } => "E0236",
+            SemaError::IncompleteRecord { .. } => "E0237",
+            SemaError::NotScalar { .. } => "E0238",
+            SemaError::EnumeratorRange { .. } => "E0239",
+            SemaError::UndeclaredEnum { .. } => "E0240",
+            SemaError::InvalidCast { .. } => "E0241",
+            SemaError::InvalidUnderlyingType { .. } => "E0242",
+            SemaError::InvalidSizeof { .. } => "E0243",
+            SemaError::InvalidReturnType { .. } => "E0244",
+            SemaError::FunctionInitialized { .. } => "E0245",
+            SemaError::IncompletePointee { .. } => "E0246",
+            SemaError::NonConstantInitializer { .. } => "E0247",
+            SemaError::NonIntegerSwitch { .. } => "E0248",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string()).with_code(self.code());
+        match self {
+            SemaError::UndefinedVariable { span, .. }
+            | SemaError::UndefinedFunction { span, .. } => {
+                diagnostic.with_primary(*span, "not found in this scope")
+            }
+            SemaError::ArgumentCount {
+                expected,
+                span,
+                declaration,
+                ..
+            } => {
+                let diagnostic = diagnostic.with_primary(
+                    *span,
+                    format!("expected {expected} argument{}", plural(expected)),
+                );
+                match declaration {
+                    Some(declaration) => {
+                        diagnostic.with_secondary(*declaration, "function declared here")
+                    }
+                    None => diagnostic,
+                }
+            }
+            SemaError::VoidValue { span, operator, .. } => {
+                let diagnostic = diagnostic.with_primary(*span, "this expression has type `void`");
+                match operator {
+                    Some(operator) => diagnostic.with_secondary(*operator, "operator"),
+                    None => diagnostic,
+                }
+            }
+            SemaError::InvalidAssignment { span, operator } => diagnostic
+                .with_primary(*span, "cannot assign to this expression")
+                .with_secondary(*operator, "assigned by this operator"),
+            SemaError::Redefinition { span, previous, .. } => {
+                let diagnostic = diagnostic.with_primary(*span, "redefined here");
+                match previous {
+                    Some(previous) => {
+                        diagnostic.with_secondary(*previous, "previous definition here")
+                    }
+                    None => diagnostic,
+                }
+            }
+            SemaError::ConflictingTypes { span, previous, .. } => diagnostic
+                .with_primary(*span, "redeclared with a different type")
+                .with_secondary(*previous, "previous declaration here"),
+            SemaError::ReturnTypeMismatch { span } => {
+                diagnostic.with_primary(*span, "returned value has an unexpected type")
+            }
+            SemaError::MissingReturnValue { span } => {
+                diagnostic.with_primary(*span, "return without a value")
+            }
+            SemaError::UnexpectedReturnValue { span } => {
+                diagnostic.with_primary(*span, "return with a value")
+            }
+            SemaError::VoidVariable { span, .. } => {
+                diagnostic.with_primary(*span, "variable cannot be of `void` type")
+            }
+            SemaError::DuplicateParameter { span, .. } => {
+                diagnostic.with_primary(*span, "used as parameter more than once")
+            }
+            SemaError::NestedFunction { span } => diagnostic
+                .with_primary(*span, "declared inside a function body")
+                .with_help("move the declaration to file scope"),
+            SemaError::ConditionalTypeMismatch {
+                then_span,
+                otherwise_span,
+                operator,
+            } => diagnostic
+                .with_primary(*operator, "branches have different types")
+                .with_secondary(*then_span, "this branch")
+                .with_secondary(*otherwise_span, "differs from this branch"),
+            SemaError::NotCallable { span, .. } => diagnostic.with_primary(*span, "called here"),
+            SemaError::NotSubscriptable { span, operator } => diagnostic
+                .with_primary(*span, "cannot be subscripted")
+                .with_secondary(*operator, "subscripted here"),
+            SemaError::NotAStructure { span, .. } => {
+                diagnostic.with_primary(*span, "no such member")
+            }
+            SemaError::BreakOutsideLoop { span } => {
+                diagnostic.with_primary(*span, "cannot `break` outside of a loop or switch")
+            }
+            SemaError::ContinueOutsideLoop { span } => {
+                diagnostic.with_primary(*span, "cannot `continue` outside of a loop")
+            }
+            SemaError::NotConstant { span } => diagnostic.with_primary(*span, "not a constant"),
+            SemaError::DuplicateCase { span, previous, .. } => diagnostic
+                .with_primary(*span, "duplicate case value")
+                .with_secondary(*previous, "previous case here"),
+            SemaError::MultipleDefault { span, previous } => diagnostic
+                .with_primary(*span, "second default label")
+                .with_secondary(*previous, "first default label here"),
+            SemaError::LabelOutsideSwitch { span, .. } => {
+                diagnostic.with_primary(*span, "not in a switch")
+            }
+            SemaError::UndefinedLabel { span, .. } => {
+                diagnostic.with_primary(*span, "no such label in this function")
+            }
+            SemaError::DuplicateLabel { span, previous, .. } => diagnostic
+                .with_primary(*span, "redefined here")
+                .with_secondary(*previous, "previous definition here"),
+            SemaError::IncompatibleType { span, .. } => {
+                diagnostic.with_primary(*span, "cannot be converted implicitly")
+            }
+            SemaError::InvalidOperands { span, .. } => {
+                diagnostic.with_primary(*span, "cannot be applied to these types")
+            }
+            SemaError::InvalidUnaryOperand { span, operator, .. } => diagnostic
+                .with_primary(*span, "operand of an invalid type")
+                .with_secondary(*operator, "operator"),
+            SemaError::AddressOfRvalue { span, operator } => diagnostic
+                .with_primary(*span, "not an lvalue")
+                .with_secondary(*operator, "address taken here"),
+            SemaError::InvalidArrayLength { span } => {
+                diagnostic.with_primary(*span, "must be greater than zero")
+            }
+            SemaError::IncompleteElementType { span, .. } => {
+                diagnostic.with_primary(*span, "declared as an array of incomplete elements")
+            }
+            SemaError::IncompleteType { span, .. } => {
+                diagnostic.with_primary(*span, "has an incomplete type")
+            }
+            SemaError::TagKindMismatch { span, previous, .. } => diagnostic
+                .with_primary(*span, "used as a different kind of tag here")
+                .with_secondary(*previous, "previous declaration here"),
+            SemaError::DuplicateMember { span, previous, .. } => diagnostic
+                .with_primary(*span, "declared again here")
+                .with_secondary(*previous, "previous declaration here"),
+            SemaError::NoSuchMember { span, .. } => {
+                diagnostic.with_primary(*span, "unknown member")
+            }
+            SemaError::IncompleteRecord { span, .. } => diagnostic
+                .with_primary(*span, "the type is not defined here")
+                .with_help("define the structure or union before accessing its members"),
+            SemaError::NotScalar { span, .. } => {
+                diagnostic.with_primary(*span, "expected an integer or pointer")
+            }
+            SemaError::EnumeratorRange { span, .. } => {
+                diagnostic.with_primary(*span, "value out of range")
+            }
+            SemaError::UndeclaredEnum { span, .. } => diagnostic
+                .with_primary(*span, "not declared")
+                .with_help("define the enumeration before using it"),
+            SemaError::InvalidCast { span, .. } => {
+                diagnostic.with_primary(*span, "only scalars can be cast")
+            }
+            SemaError::InvalidUnderlyingType { span, .. } => {
+                diagnostic.with_primary(*span, "declared with this underlying type")
+            }
+            SemaError::InvalidSizeof { span, .. } => {
+                diagnostic.with_primary(*span, "the size of this type is not known")
+            }
+            SemaError::InvalidReturnType { span, .. } => diagnostic
+                .with_primary(*span, "declared here")
+                .with_help("return a pointer instead"),
+            SemaError::FunctionInitialized { span, .. } => {
+                diagnostic.with_primary(*span, "declared as a function")
+            }
+            SemaError::IncompletePointee { span, .. } => {
+                diagnostic.with_primary(*span, "needs the size of the pointee")
+            }
+            SemaError::NonConstantInitializer { span } => diagnostic
+                .with_primary(*span, "not constant")
+                .with_note("objects at file scope are initialized before the program runs"),
+            SemaError::NonIntegerSwitch { span, .. } => {
+                diagnostic.with_primary(*span, "expected an integer")
+            }
+            SemaError::Unsupported { span, .. } => diagnostic.with_primary(*span, "not supported"),
+            SemaError::Internal { dumped, .. } => {
+                let diagnostic = diagnostic.with_note("this is a bug in rcc, not in your code");
+                if *dumped {
+                    diagnostic
+                } else {
+                    diagnostic.with_help("rerun with `--dump-on-ice` to print the LLVM module")
+                }
+            }
+        }
+    }
+}
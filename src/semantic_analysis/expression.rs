@@ -1,249 +1,1205 @@
 use either::Either;
-use inkwell::values::{BasicValue, BasicValueEnum};
+use inkwell::{
+    basic_block::BasicBlock,
+    types::BasicTypeEnum,
+    values::{IntValue, PointerValue},
+    IntPredicate,
+};
 
 use crate::{
-    lexical_analysis::Constant,
-    syntax_analysis::expression::{BinaryOperator, Expression, PrefixUnaryOperator, RefOrCall},
+    diagnostics::{Diagnostic, Span},
+    syntax_analysis::{
+        expression::{
+            BinaryOperator, Expression, PostfixUnaryOperator, PrefixUnaryOperator, RefOrCall,
+        },
+        types::{IntegerType, Type},
+    },
+};
+
+use super::{
+    constant::IntegerConstant,
+    types::{common_type, composite_pointer_type, Value, PTRDIFF_T, SIZE_T},
+    SemaError, Signature, Variable, IR,
 };
 
-use super::IR;
+/// What an lvalue is needed for, to explain why other expressions are not
+/// allowed.
+#[derive(Debug, Clone, Copy)]
+enum LvalueUse {
+    /// Assigned to, incremented or decremented by the operator.
+    Assignment(Span),
+    /// Operand of `&`.
+    AddressOf(Span),
+}
 
 impl<'ctx> IR<'ctx> {
+    /// Whether `expr` designates an object, rather than only having a value.
+    fn is_lvalue(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Paren(expr, _) | Expression::Member(expr, _, _) => self.is_lvalue(expr),
+            Expression::RefOrCall(RefOrCall::Variable(name, _)) => {
+                self.enum_constant(name).is_none()
+            }
+            Expression::PrefixUnary(PrefixUnaryOperator::Deref, _, _)
+            | Expression::Subscript(..)
+            | Expression::PointerMember(..) => true,
+            _ => false,
+        }
+    }
+
     pub(super) fn analysis_expression(
-        &self,
+        &mut self,
         expr: Expression,
-    ) -> anyhow::Result<Option<BasicValueEnum<'ctx>>> {
+    ) -> anyhow::Result<Option<Value<'ctx>>> {
         tracing::trace!("Emit {expr:?}");
         match expr {
-            Expression::Evaluate(expr) => match self.analysis_expression(*expr)? {
-                None => Ok(None),
-                Some(value) => match value {
-                    BasicValueEnum::PointerValue(ptr) => {
-                        let value =
-                            self.builder
-                                .build_load(self.context.i32_type(), ptr, "load")?;
-                        Ok(Some(value))
-                    }
-                    _ => Ok(Some(value)),
-                },
-            },
-            Expression::Binary(lhs, bin_op, rhs) => {
-                if matches!(bin_op, BinaryOperator::Assign) {
-                    let Expression::RefOrCall(RefOrCall::Variable(lhs)) = *lhs else {
-                        anyhow::bail!("Only variable can be assigned");
-                    };
-                    let Some(rhs) = self.analysis_expression(*rhs)? else {
-                        anyhow::bail!("Cannot assign void to variable");
-                    };
-                    let Some(lhs) = self.local_variables.get(&lhs) else {
-                        anyhow::bail!("Undefined variable: {lhs}");
-                    };
-                    tracing::trace!("assigning {rhs} to {lhs}");
-                    // anyhow::ensure!(
-                    //     lhs.as_basic_value_enum().get_type() == rhs.get_type(),
-                    //     "Cannot assign a value of different type"
-                    // );
-                    match bin_op {
-                        BinaryOperator::Assign => {
-                            self.builder.build_store(lhs.to_owned(), rhs)?;
-                            Ok(Some(lhs.as_basic_value_enum()))
-                        }
-                        _ => unreachable!(),
-                    }
+            // lvalues are loaded where they are lowered as values
+            Expression::Evaluate(expr) | Expression::Paren(expr, _) => {
+                self.analysis_expression(*expr)
+            }
+            Expression::Binary(lhs, bin_op, rhs, span) => {
+                if bin_op.is_assignment() {
+                    self.analysis_assignment(*lhs, bin_op, *rhs, span).map(Some)
+                } else if matches!(bin_op, BinaryOperator::Comma) {
+                    // the left operand is evaluated for its side effects only
+                    self.analysis_expression(*lhs)?;
+                    self.analysis_expression(*rhs)
+                } else if matches!(
+                    bin_op,
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr
+                ) {
+                    self.analysis_logical(*lhs, bin_op, *rhs, span).map(Some)
+                } else if bin_op.is_comparison() {
+                    // pointers compare with null pointer constants
+                    let lhs = self.analysis_converted_operand(*lhs, span)?;
+                    let rhs_span = rhs.span();
+                    let rhs = self.analysis_converted_operand(*rhs, span)?;
+                    self.build_binary(lhs, bin_op, rhs, span, rhs_span)
+                        .map(Some)
                 } else {
-                    use inkwell::IntPredicate;
-                    let Some(lhs) = self.analysis_expression(*lhs)? else {
-                        anyhow::bail!("Operand cannot be void");
-                    };
-                    let Some(rhs) = self.analysis_expression(*rhs)? else {
-                        anyhow::bail!("Operand cannot be void");
-                    };
-                    tracing::trace!("doing {} {:?} {}", lhs, bin_op, rhs);
-                    let result = match bin_op {
-                        BinaryOperator::Multiply => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_mul(lhs, rhs, "mul")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Divide => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_signed_div(lhs, rhs, "signed_div")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Plus => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_add(lhs, rhs, "add")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Minus => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_sub(lhs, rhs, "sub")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Less => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_compare(IntPredicate::SLT, lhs, rhs, "slt")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Greater => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_compare(IntPredicate::SGT, lhs, rhs, "sgt")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::LessEqual => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_compare(IntPredicate::SLE, lhs, rhs, "sle")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::GreaterEqual => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_compare(IntPredicate::SGE, lhs, rhs, "sge")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Equal => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_compare(IntPredicate::EQ, lhs, rhs, "eq")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::NotEqual => match lhs {
-                            BasicValueEnum::IntValue(lhs) => match rhs {
-                                BasicValueEnum::IntValue(rhs) => self
-                                    .builder
-                                    .build_int_compare(IntPredicate::NE, lhs, rhs, "ne")?
-                                    .as_basic_value_enum(),
-                                _ => anyhow::bail!("Operand of unimplemented type"),
-                            },
-                            _ => anyhow::bail!("Operand of unimplemented type"),
-                        },
-                        BinaryOperator::Comma => {
-                            anyhow::bail!("Comma expression not implemented")
-                        }
-                        BinaryOperator::Assign => unreachable!(),
-                    };
-                    Ok(Some(result))
+                    let lhs = self.analysis_operand(*lhs, span)?;
+                    let rhs_span = rhs.span();
+                    let rhs = self.analysis_operand(*rhs, span)?;
+                    tracing::trace!("doing {:?} {:?} {:?}", lhs, bin_op, rhs);
+                    self.build_binary(lhs, bin_op, rhs, span, rhs_span)
+                        .map(Some)
+                }
+            }
+            Expression::PrefixUnary(
+                op @ (PrefixUnaryOperator::Increment | PrefixUnaryOperator::Decrement),
+                operand,
+                span,
+            ) => {
+                let increment = matches!(op, PrefixUnaryOperator::Increment);
+                self.analysis_increment(*operand, increment, true, span)
+                    .map(Some)
+            }
+            Expression::PostfixUnary(op, operand, span) => {
+                let increment = matches!(op, PostfixUnaryOperator::Increment);
+                self.analysis_increment(*operand, increment, false, span)
+                    .map(Some)
+            }
+            Expression::PrefixUnary(PrefixUnaryOperator::AddressOf, operand, span) => {
+                let Variable { pointer, ty, .. } =
+                    self.analysis_lvalue(*operand, Some(LvalueUse::AddressOf(span)))?;
+                Ok(Some(Value {
+                    value: pointer.into(),
+                    ty: Type::Pointer(Box::new(ty)),
+                    null_pointer: false,
+                }))
+            }
+            Expression::RefOrCall(RefOrCall::Variable(name, _))
+                if self.enum_constant(&name).is_some() =>
+            {
+                let constant = self.enum_constant(&name).unwrap();
+                Ok(Some(self.enum_value(constant)))
+            }
+            expr @ (Expression::RefOrCall(_)
+            | Expression::PrefixUnary(PrefixUnaryOperator::Deref, _, _)
+            | Expression::Subscript(..)
+            | Expression::Member(..)
+            | Expression::PointerMember(..)) => {
+                let span = expr.span();
+                let Variable { pointer, ty, .. } = self.analysis_lvalue(expr, None)?;
+                match ty {
+                    // `*p` with `p` of type `void *` is a `void` expression
+                    Type::Void => Ok(None),
+                    // an array decays to a pointer to its first element
+                    Type::Array(element, _) => Ok(Some(Value {
+                        value: pointer.into(),
+                        ty: Type::Pointer(element),
+                        null_pointer: false,
+                    })),
+                    // and a function to a pointer to it
+                    ty @ Type::Function(..) => Ok(Some(Value {
+                        value: pointer.into(),
+                        ty: Type::Pointer(Box::new(ty)),
+                        null_pointer: false,
+                    })),
+                    ty => self.load(pointer, ty, span).map(Some),
+                }
+            }
+            Expression::Cast(ty, operand, span) => {
+                let ty = self.resolve_type(ty, span)?;
+                if ty == Type::Void {
+                    // evaluated for its side effects only
+                    self.analysis_expression(*operand)?;
+                    return Ok(None);
                 }
+                let operand = self.analysis_operand(*operand, span)?;
+                self.cast(operand, &ty, span).map(Some)
+            }
+            Expression::Sizeof(operand, span) => {
+                let span = span.to(operand.span());
+                let ty = self.type_of(&operand)?;
+                self.size_value(ty, span).map(Some)
+            }
+            Expression::SizeofType(ty, span) => {
+                let ty = self.resolve_type(ty, span)?;
+                self.size_value(ty, span).map(Some)
             }
-            Expression::PrefixUnary(op, operand) => {
-                use inkwell::IntPredicate;
-                let Some(operand) = self.analysis_expression(*operand)? else {
-                    anyhow::bail!("Operand cannot be void");
+            Expression::PrefixUnary(op, operand, span) => {
+                let operand_span = operand.span();
+                let operand = self.analysis_operand(*operand, span)?;
+                if let PrefixUnaryOperator::Not = op {
+                    let truth = self.scalar_truth_value(operand, operand_span)?;
+                    let not = self.builder.build_not(truth, "not")?;
+                    return self.bool_to_int(not).map(Some);
+                }
+                let operand = self.promote(operand)?;
+                let Type::Integer(ty) = operand.ty else {
+                    anyhow::bail!(SemaError::InvalidUnaryOperand {
+                        ty: operand.ty,
+                        span: operand_span,
+                        operator: span,
+                    });
                 };
+                let operand = operand.value.into_int_value();
                 let result = match op {
-                    PrefixUnaryOperator::Minus => match operand {
-                        BasicValueEnum::IntValue(operand) => Some(
-                            self.builder
-                                .build_int_neg(operand, "neg")?
-                                .as_basic_value_enum(),
-                        ),
-                        _ => anyhow::bail!("Uninplemented operand type"),
-                    },
-                    PrefixUnaryOperator::Plus => None,
-                    PrefixUnaryOperator::Not => match operand {
-                        BasicValueEnum::IntValue(operand) => Some(
-                            self.builder
-                                .build_int_compare(
-                                    IntPredicate::EQ,
-                                    operand,
-                                    self.context.i32_type().const_zero(),
-                                    "not",
-                                )?
-                                .as_basic_value_enum(),
-                        ),
-                        _ => anyhow::bail!("Uninplemented operand type"),
-                    },
+                    PrefixUnaryOperator::Minus if ty.signed => {
+                        self.builder.build_int_nsw_neg(operand, "neg")?
+                    }
+                    PrefixUnaryOperator::Minus => self.builder.build_int_neg(operand, "neg")?,
+                    PrefixUnaryOperator::Plus => operand,
+                    PrefixUnaryOperator::BitNot => self.builder.build_not(operand, "bit_not")?,
+                    PrefixUnaryOperator::Not
+                    | PrefixUnaryOperator::Increment
+                    | PrefixUnaryOperator::Decrement
+                    | PrefixUnaryOperator::AddressOf
+                    | PrefixUnaryOperator::Deref => {
+                        unreachable!()
+                    }
                 };
-                Ok(result)
-            }
-            Expression::Constant(value) => match value {
-                Constant::Int(value) => Ok(Some(
-                    self.context
-                        .i32_type()
-                        .const_int(value as u64, true)
-                        .as_basic_value_enum(),
-                )),
-            },
-            Expression::Paren(expr) => self.analysis_expression(*expr),
-            Expression::RefOrCall(r) => match r {
-                RefOrCall::Variable(var) => {
-                    if let Some(local) = self.local_variables.get(&var) {
-                        Ok(Some(local.as_basic_value_enum()))
-                    } else if let Some(global) = self.module.get_global(&var) {
-                        let global = global.as_pointer_value();
-                        Ok(Some(global.as_basic_value_enum()))
-                    } else {
-                        anyhow::bail!("Undefined variable: {var}");
+                Ok(Some(Value::int(result, ty)))
+            }
+            Expression::Conditional(condition, then, otherwise, span) => {
+                self.analysis_conditional(*condition, *then, *otherwise, span)
+            }
+            Expression::Constant(constant, _) => {
+                Ok(Some(self.constant_value(IntegerConstant::from(&constant))))
+            }
+            Expression::Call(callee, args, span) => self.analysis_call(*callee, args, span),
+        }
+    }
+
+    /// The size of `ty` as the value of `sizeof` at `span`.
+    fn size_value(&self, ty: Type, span: Span) -> anyhow::Result<Value<'ctx>> {
+        let size = self.size_of(ty, span)?;
+        let size = self.context.i64_type().const_int(size, false);
+        Ok(Value::int(size, SIZE_T))
+    }
+
+    /// Type of `expr`, the operand of `sizeof`, which is not evaluated. An
+    /// array or function operand does not decay to a pointer.
+    pub(super) fn type_of(&mut self, expr: &Expression) -> anyhow::Result<Type> {
+        let expr = expr.clone();
+        self.unevaluated(|ir| {
+            if ir.is_lvalue(&expr) {
+                Ok(ir.analysis_lvalue(expr, None)?.ty)
+            } else {
+                Ok(ir
+                    .analysis_expression(expr)?
+                    .map_or(Type::Void, |value| value.ty))
+            }
+        })
+    }
+
+    /// Type of the value of `expr`, `void` if it has none, without
+    /// evaluating it.
+    pub(super) fn value_type(&mut self, expr: &Expression) -> anyhow::Result<Type> {
+        let expr = expr.clone();
+        self.unevaluated(|ir| {
+            Ok(ir
+                .analysis_expression(expr)?
+                .map_or(Type::Void, |value| value.ty))
+        })
+    }
+
+    /// Run `lower`, which lowers an expression that is not evaluated, into a
+    /// function of a module of its own, which is dropped afterwards along
+    /// with the warnings found. Tags and enumeration constants it declares
+    /// at file scope, e.g. in a cast, are kept there.
+    fn unevaluated<T>(
+        &mut self,
+        lower: impl FnOnce(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let insert_block = self.builder.get_insert_block();
+        let diagnostics = self.diagnostics.len();
+        let module = self.context.create_module("unevaluated");
+        let fn_type = self.context.void_type().fn_type(&[], false);
+        let function = module.add_function("unevaluated", fn_type, None);
+        let entry = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(entry);
+        let result = lower(self);
+        self.diagnostics.truncate(diagnostics);
+        match insert_block {
+            Some(block) => self.builder.position_at_end(block),
+            None => {
+                self.builder.clear_insertion_position();
+                for tag in std::mem::take(&mut self.scope_tags) {
+                    let declaration = self.local_tags.remove(&tag).unwrap();
+                    self.global_tags.insert(tag, declaration);
+                }
+                // only enumeration constants are declared by expressions
+                for name in std::mem::take(&mut self.scope_variables) {
+                    if let Some(constant) = self.local_enumerators.remove(&name) {
+                        self.global_enumerators.insert(name, constant);
                     }
                 }
-                RefOrCall::FunctionCall(func, args) => {
-                    let Some(func) = self.module.get_function(&func) else {
-                        anyhow::bail!("Undefined function: {func}");
-                    };
-                    let params_len = func.get_type().get_param_types().len();
-                    if args.len() != params_len {
-                        anyhow::bail!("Expected {} arguments, found {}", params_len, args.len());
+            }
+        }
+        result
+    }
+
+    /// Lower an operand of the operator at `operator`, which cannot be `void`.
+    fn analysis_operand(
+        &mut self,
+        expr: Expression,
+        operator: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let span = expr.span();
+        match self.analysis_expression(expr)? {
+            Some(value) => Ok(value),
+            None => anyhow::bail!(SemaError::VoidValue {
+                what: "operand",
+                span,
+                operator: Some(operator),
+            }),
+        }
+    }
+
+    /// Lower `expr`, whose value is converted to another type, noting whether
+    /// it is a null pointer constant.
+    pub(super) fn analysis_converted(
+        &mut self,
+        expr: Expression,
+    ) -> anyhow::Result<Option<Value<'ctx>>> {
+        let null_pointer = self.is_null_pointer_constant(&expr);
+        let value = self.analysis_expression(expr)?;
+        Ok(value.map(|value| Value {
+            null_pointer,
+            ..value
+        }))
+    }
+
+    /// Lower an operand of the operator at `operator` like
+    /// `analysis_operand`, noting whether it is a null pointer constant.
+    fn analysis_converted_operand(
+        &mut self,
+        expr: Expression,
+        operator: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let null_pointer = self.is_null_pointer_constant(&expr);
+        let value = self.analysis_operand(expr, operator)?;
+        Ok(Value {
+            null_pointer,
+            ..value
+        })
+    }
+
+    /// Lower a call. A function called by name is called directly, and any
+    /// other callee through the pointer to a function it evaluates to.
+    fn analysis_call(
+        &mut self,
+        callee: Expression,
+        args: Vec<Expression>,
+        span: Span,
+    ) -> anyhow::Result<Option<Value<'ctx>>> {
+        let (callee, signature, name) = match callee {
+            Expression::Paren(callee, _) => return self.analysis_call(*callee, args, span),
+            Expression::RefOrCall(RefOrCall::Variable(name, callee_span))
+                if !self.is_object(&name) =>
+            {
+                let Some(func) = self.module.get_function(&name) else {
+                    anyhow::bail!(SemaError::UndefinedFunction {
+                        name,
+                        span: callee_span,
+                    });
+                };
+                let Some(signature) = self.functions.get(&name).cloned() else {
+                    anyhow::bail!("function `{name}` has no signature");
+                };
+                (Either::Left(func), signature, Some(name))
+            }
+            callee => {
+                let callee_span = callee.span();
+                let pointer = self.analysis_operand(callee, span)?;
+                let Type::Pointer(pointee) = &pointer.ty else {
+                    anyhow::bail!(SemaError::NotCallable {
+                        ty: pointer.ty,
+                        span: callee_span,
+                    });
+                };
+                let Type::Function(return_type, params) = &**pointee else {
+                    anyhow::bail!(SemaError::NotCallable {
+                        ty: pointer.ty,
+                        span: callee_span,
+                    });
+                };
+                let signature = Signature {
+                    return_type: (**return_type).clone(),
+                    params: params.clone(),
+                };
+                (
+                    Either::Right(pointer.value.into_pointer_value()),
+                    signature,
+                    None,
+                )
+            }
+        };
+        if signature.return_type != Type::Void {
+            self.check_complete(&signature.return_type, span)?;
+        }
+        if args.len() != signature.params.len() {
+            anyhow::bail!(SemaError::ArgumentCount {
+                expected: signature.params.len(),
+                found: args.len(),
+                span,
+                declaration: name
+                    .as_ref()
+                    .and_then(|name| self.function_spans.get(name).copied()),
+                name,
+            });
+        }
+        let mut parsed_args = Vec::new();
+        for (arg, param_type) in args.into_iter().zip(&signature.params) {
+            let arg_span = arg.span();
+            let Some(arg) = self.analysis_converted(arg)? else {
+                anyhow::bail!(SemaError::VoidValue {
+                    what: "argument",
+                    span: arg_span,
+                    operator: None,
+                });
+            };
+            let Some(arg) = self.convert(arg, param_type)? else {
+                anyhow::bail!(SemaError::IncompatibleType {
+                    what: "argument",
+                    span: arg_span,
+                });
+            };
+            parsed_args.push(arg.into());
+        }
+        let callsite = match callee {
+            Either::Left(func) => self.builder.build_call(func, &parsed_args, "call")?,
+            Either::Right(pointer) => {
+                let fn_type = self.fn_type(&signature);
+                self.builder
+                    .build_indirect_call(fn_type, pointer, &parsed_args, "call")?
+            }
+        };
+        match callsite.try_as_basic_value() {
+            Either::Left(value) => Ok(Some(Value {
+                value,
+                ty: signature.return_type,
+                null_pointer: false,
+            })),
+            Either::Right(_) => Ok(None),
+        }
+    }
+
+    /// Apply an arithmetic, bitwise or comparison operator to integers, after
+    /// the usual arithmetic conversions. Comparisons yield an `int` 0 or 1.
+    fn build_binary(
+        &mut self,
+        lhs: Value<'ctx>,
+        bin_op: BinaryOperator,
+        rhs: Value<'ctx>,
+        operator: Span,
+        rhs_span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        if matches!(lhs.ty, Type::Pointer(_)) || matches!(rhs.ty, Type::Pointer(_)) {
+            return self.build_pointer_binary(lhs, bin_op, rhs, operator);
+        }
+        if let BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight = bin_op {
+            return self.build_shift(lhs, bin_op, rhs, operator, rhs_span);
+        }
+        let (lhs, rhs, ty) = self.usual_arithmetic_conversions(lhs, rhs, operator)?;
+        let builder = &self.builder;
+        let predicate = match (bin_op, ty.signed) {
+            (BinaryOperator::Less, true) => Some(IntPredicate::SLT),
+            (BinaryOperator::Less, false) => Some(IntPredicate::ULT),
+            (BinaryOperator::Greater, true) => Some(IntPredicate::SGT),
+            (BinaryOperator::Greater, false) => Some(IntPredicate::UGT),
+            (BinaryOperator::LessEqual, true) => Some(IntPredicate::SLE),
+            (BinaryOperator::LessEqual, false) => Some(IntPredicate::ULE),
+            (BinaryOperator::GreaterEqual, true) => Some(IntPredicate::SGE),
+            (BinaryOperator::GreaterEqual, false) => Some(IntPredicate::UGE),
+            (BinaryOperator::Equal, _) => Some(IntPredicate::EQ),
+            (BinaryOperator::NotEqual, _) => Some(IntPredicate::NE),
+            _ => None,
+        };
+        if let Some(predicate) = predicate {
+            let result = builder.build_int_compare(predicate, lhs, rhs, "cmp")?;
+            return self.bool_to_int(result);
+        }
+        // signed overflow is undefined, so signed arithmetic is `nsw`
+        let result = match (bin_op, ty.signed) {
+            (BinaryOperator::Multiply, true) => builder.build_int_nsw_mul(lhs, rhs, "mul")?,
+            (BinaryOperator::Multiply, false) => builder.build_int_mul(lhs, rhs, "mul")?,
+            (BinaryOperator::Divide, true) => builder.build_int_signed_div(lhs, rhs, "div")?,
+            (BinaryOperator::Divide, false) => builder.build_int_unsigned_div(lhs, rhs, "div")?,
+            (BinaryOperator::Modulo, true) => builder.build_int_signed_rem(lhs, rhs, "rem")?,
+            (BinaryOperator::Modulo, false) => builder.build_int_unsigned_rem(lhs, rhs, "rem")?,
+            (BinaryOperator::Plus, true) => builder.build_int_nsw_add(lhs, rhs, "add")?,
+            (BinaryOperator::Plus, false) => builder.build_int_add(lhs, rhs, "add")?,
+            (BinaryOperator::Minus, true) => builder.build_int_nsw_sub(lhs, rhs, "sub")?,
+            (BinaryOperator::Minus, false) => builder.build_int_sub(lhs, rhs, "sub")?,
+            (BinaryOperator::BitAnd, _) => builder.build_and(lhs, rhs, "and")?,
+            (BinaryOperator::BitXor, _) => builder.build_xor(lhs, rhs, "xor")?,
+            (BinaryOperator::BitOr, _) => builder.build_or(lhs, rhs, "or")?,
+            _ => unreachable!("{bin_op:?} is not an arithmetic operator"),
+        };
+        Ok(Value::int(result, ty))
+    }
+
+    /// Apply an operator with a pointer operand: adding an integer to or
+    /// subtracting one from a pointer moves it by that many pointees,
+    /// subtracting pointers yields the number of pointees between them as a
+    /// `ptrdiff_t`, and comparisons compare addresses.
+    fn build_pointer_binary(
+        &mut self,
+        lhs: Value<'ctx>,
+        bin_op: BinaryOperator,
+        rhs: Value<'ctx>,
+        operator: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let invalid = SemaError::InvalidOperands {
+            lhs: lhs.ty.clone(),
+            rhs: rhs.ty.clone(),
+            span: operator,
+        };
+        let predicate = match bin_op {
+            BinaryOperator::Less => IntPredicate::ULT,
+            BinaryOperator::Greater => IntPredicate::UGT,
+            BinaryOperator::LessEqual => IntPredicate::ULE,
+            BinaryOperator::GreaterEqual => IntPredicate::UGE,
+            BinaryOperator::Equal => IntPredicate::EQ,
+            BinaryOperator::NotEqual => IntPredicate::NE,
+            BinaryOperator::Plus | BinaryOperator::Minus => {
+                return match (&lhs.ty, &rhs.ty) {
+                    (Type::Pointer(_), Type::Integer(_)) => {
+                        let negate = matches!(bin_op, BinaryOperator::Minus);
+                        self.offset_pointer(lhs, rhs, negate, operator)
                     }
-                    let mut parsed_args = Vec::new();
-                    for arg in args {
-                        let Some(arg) = self.analysis_expression(arg)? else {
-                            anyhow::bail!("Argument cannot be void");
-                        };
-                        match arg {
-                            BasicValueEnum::IntValue(arg) => parsed_args.push(arg.into()),
-                            _ => anyhow::bail!("Invalid argument type"),
-                        }
+                    (Type::Integer(_), Type::Pointer(_))
+                        if matches!(bin_op, BinaryOperator::Plus) =>
+                    {
+                        self.offset_pointer(rhs, lhs, false, operator)
                     }
-                    let callsite = self.builder.build_call(func, &parsed_args, "call")?;
-                    match callsite.try_as_basic_value() {
-                        Either::Left(value) => Ok(Some(value)),
-                        Either::Right(_) => Ok(None),
+                    (Type::Pointer(lhs_pointee), Type::Pointer(rhs_pointee))
+                        if matches!(bin_op, BinaryOperator::Minus)
+                            && lhs_pointee == rhs_pointee =>
+                    {
+                        let pointee_type = self.sized_pointee(lhs_pointee, operator)?;
+                        let diff = self.builder.build_ptr_diff(
+                            pointee_type,
+                            lhs.value.into_pointer_value(),
+                            rhs.value.into_pointer_value(),
+                            "ptr_diff",
+                        )?;
+                        Ok(Value::int(diff, PTRDIFF_T))
                     }
+                    _ => anyhow::bail!(invalid),
+                };
+            }
+            _ => anyhow::bail!(invalid),
+        };
+        let Some(ty) = composite_pointer_type(&lhs, &rhs) else {
+            anyhow::bail!(invalid);
+        };
+        let lhs = self.convert(lhs, &ty)?.unwrap().into_pointer_value();
+        let rhs = self.convert(rhs, &ty)?.unwrap().into_pointer_value();
+        let result = self.builder.build_int_compare(predicate, lhs, rhs, "cmp")?;
+        self.bool_to_int(result)
+    }
+
+    /// LLVM type of the pointee of a pointer in arithmetic by the operator
+    /// at `operator`, which must be a complete object type: there is no
+    /// arithmetic on `void *` or pointers to incomplete structures.
+    fn sized_pointee(&self, pointee: &Type, operator: Span) -> anyhow::Result<BasicTypeEnum<'ctx>> {
+        match self.llvm_type(pointee) {
+            Some(pointee_type) if self.is_complete(pointee) => Ok(pointee_type),
+            _ => anyhow::bail!(SemaError::IncompletePointee {
+                ty: pointee.clone(),
+                span: operator,
+            }),
+        }
+    }
+
+    /// Move `pointer` by `offset` pointees, backwards if `negate`, for the
+    /// operator at `operator`.
+    fn offset_pointer(
+        &mut self,
+        pointer: Value<'ctx>,
+        offset: Value<'ctx>,
+        negate: bool,
+        operator: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let (Type::Pointer(pointee), Type::Integer(offset_type)) = (&pointer.ty, offset.ty) else {
+            unreachable!("offset_pointer takes a pointer and an integer");
+        };
+        let pointee_type = self.sized_pointee(pointee, operator)?;
+        let mut offset = self.cast_int(offset.value.into_int_value(), offset_type, PTRDIFF_T)?;
+        if negate {
+            offset = self.builder.build_int_neg(offset, "neg")?;
+        }
+        // SAFETY: C only defines pointer arithmetic within one object, which
+        // is exactly what `inbounds` asserts
+        let value = unsafe {
+            self.builder.build_in_bounds_gep(
+                pointee_type,
+                pointer.value.into_pointer_value(),
+                &[offset],
+                "ptr_add",
+            )?
+        };
+        Ok(Value {
+            value: value.into(),
+            ty: pointer.ty,
+            null_pointer: false,
+        })
+    }
+
+    /// Lower `<<` and `>>` at `operator`. The operands are promoted
+    /// separately, and the result has the type of the left one.
+    fn build_shift(
+        &mut self,
+        lhs: Value<'ctx>,
+        bin_op: BinaryOperator,
+        rhs: Value<'ctx>,
+        operator: Span,
+        rhs_span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let (Type::Integer(_), Type::Integer(_)) = (&lhs.ty, &rhs.ty) else {
+            anyhow::bail!(SemaError::InvalidOperands {
+                lhs: lhs.ty,
+                rhs: rhs.ty,
+                span: operator,
+            });
+        };
+        let (lhs, rhs) = (self.promote(lhs)?, self.promote(rhs)?);
+        let (Type::Integer(ty), Type::Integer(rhs_type)) = (&lhs.ty, &rhs.ty) else {
+            unreachable!("integers promote to integers");
+        };
+        let (ty, rhs_type) = (*ty, *rhs_type);
+        let lhs = lhs.value.into_int_value();
+        let rhs = rhs.value.into_int_value();
+        self.check_shift_count(lhs, rhs, rhs_type, rhs_span)?;
+        let count_type = IntegerType {
+            signed: rhs_type.signed,
+            ..ty
+        };
+        let rhs = self.cast_int(rhs, rhs_type, count_type)?;
+        let result = match bin_op {
+            BinaryOperator::ShiftLeft => self.builder.build_left_shift(lhs, rhs, "shl")?,
+            _ => self.builder.build_right_shift(lhs, rhs, ty.signed, "shr")?,
+        };
+        Ok(Value::int(result, ty))
+    }
+
+    /// Whether `name` refers to a variable or an enumeration constant,
+    /// rather than to a function or nothing.
+    fn is_object(&self, name: &str) -> bool {
+        self.local_variables.contains_key(name)
+            || self.enum_constant(name).is_some()
+            || self.global_variables.contains_key(name)
+    }
+
+    /// A variable in scope, or a function, whose name designates it.
+    pub(super) fn variable(&self, name: String, span: Span) -> anyhow::Result<Variable<'ctx>> {
+        if let Some(local) = self.local_variables.get(&name) {
+            Ok(local.to_owned())
+        } else if let Some(global) = self.global_variables.get(&name) {
+            Ok(global.to_owned())
+        } else if let Some(Signature {
+            return_type,
+            params,
+        }) = self.functions.get(&name)
+        {
+            let Some(function) = self.module.get_function(&name) else {
+                anyhow::bail!("function `{name}` is not in the module");
+            };
+            Ok(Variable {
+                pointer: function.as_global_value().as_pointer_value(),
+                ty: Type::Function(Box::new(return_type.clone()), params.clone()),
+                span,
+            })
+        } else {
+            anyhow::bail!(SemaError::UndefinedVariable { name, span });
+        }
+    }
+
+    /// Address and type of an lvalue, e.g. one assigned to by an operator.
+    fn analysis_lvalue(
+        &mut self,
+        expr: Expression,
+        usage: Option<LvalueUse>,
+    ) -> anyhow::Result<Variable<'ctx>> {
+        match expr {
+            Expression::Paren(expr, _) => self.analysis_lvalue(*expr, usage),
+            Expression::RefOrCall(RefOrCall::Variable(name, span))
+                if self.enum_constant(&name).is_none() =>
+            {
+                self.variable(name, span)
+            }
+            Expression::PrefixUnary(PrefixUnaryOperator::Deref, operand, operator) => {
+                let span = operand.span();
+                let pointer = self.analysis_operand(*operand, operator)?;
+                let Type::Pointer(pointee) = pointer.ty else {
+                    anyhow::bail!(SemaError::InvalidUnaryOperand {
+                        ty: pointer.ty,
+                        span,
+                        operator,
+                    });
+                };
+                Ok(Variable {
+                    pointer: pointer.value.into_pointer_value(),
+                    ty: *pointee,
+                    span,
+                })
+            }
+            Expression::Subscript(array, index, span) => {
+                // `array[index]` is `*(array + index)`
+                let array_span = array.span();
+                let array = self.analysis_operand(*array, span)?;
+                let index = self.analysis_operand(*index, span)?;
+                let element = match (&array.ty, &index.ty) {
+                    (Type::Pointer(element), Type::Integer(_))
+                    | (Type::Integer(_), Type::Pointer(element)) => (**element).clone(),
+                    _ => anyhow::bail!(SemaError::NotSubscriptable {
+                        span: array_span,
+                        operator: span,
+                    }),
+                };
+                let pointer = self.build_binary(array, BinaryOperator::Plus, index, span, span)?;
+                Ok(Variable {
+                    pointer: pointer.value.into_pointer_value(),
+                    ty: element,
+                    span,
+                })
+            }
+            Expression::Member(object, member, span) => {
+                let object = match *object {
+                    // the members of a structure returned by a call can be read
+                    object if usage.is_none() && !self.is_lvalue(&object) => {
+                        let object_span = object.span();
+                        let value = self.analysis_operand(object, span)?;
+                        let Type::Record(_) = value.ty else {
+                            anyhow::bail!(SemaError::NotAStructure { member, span });
+                        };
+                        self.spill(value, object_span)?
+                    }
+                    object => self.analysis_lvalue(object, usage)?,
+                };
+                self.member(object, member, span)
+            }
+            Expression::PointerMember(pointer, member, span) => {
+                let pointer = self.analysis_operand(*pointer, span)?;
+                let Type::Pointer(pointee) = pointer.ty else {
+                    anyhow::bail!(SemaError::NotAStructure { member, span });
+                };
+                let object = Variable {
+                    pointer: pointer.value.into_pointer_value(),
+                    ty: *pointee,
+                    span,
+                };
+                self.member(object, member, span)
+            }
+            expr => match usage {
+                Some(LvalueUse::Assignment(operator)) => {
+                    anyhow::bail!(SemaError::InvalidAssignment {
+                        span: expr.span(),
+                        operator,
+                    })
                 }
+                Some(LvalueUse::AddressOf(operator)) => {
+                    anyhow::bail!(SemaError::AddressOfRvalue {
+                        span: expr.span(),
+                        operator,
+                    })
+                }
+                None => anyhow::bail!("{expr:?} is not an lvalue"),
+            },
+        }
+    }
+
+    /// The member `member` of the structure or union `object`, accessed at
+    /// `span`. Members of anonymous members are found through them.
+    fn member(
+        &self,
+        object: Variable<'ctx>,
+        member: String,
+        span: Span,
+    ) -> anyhow::Result<Variable<'ctx>> {
+        let path = self.member_path(&object.ty, member, span)?;
+        let (mut pointer, mut ty) = (object.pointer, object.ty);
+        for position in path {
+            let Type::Record(record) = &ty else {
+                unreachable!("only records have members");
+            };
+            (pointer, ty) = self.member_pointer(pointer, record, position)?;
+        }
+        Ok(Variable { pointer, ty, span })
+    }
+
+    /// Positions leading from a structure or union of type `ty` to its
+    /// member `member`, accessed at `span`, through anonymous members.
+    fn member_path(&self, ty: &Type, member: String, span: Span) -> anyhow::Result<Vec<usize>> {
+        let Type::Record(record) = ty else {
+            anyhow::bail!(SemaError::NotAStructure { member, span });
+        };
+        if !self.is_defined(record) {
+            anyhow::bail!(SemaError::IncompleteRecord {
+                ty: ty.clone(),
+                span,
+            });
+        }
+        let Some(path) = self.find_member(record, &member) else {
+            anyhow::bail!(SemaError::NoSuchMember {
+                member,
+                ty: ty.clone(),
+                span,
+            });
+        };
+        Ok(path)
+    }
+
+    /// Store a value that is not an lvalue, at `span`, in a temporary
+    /// object.
+    fn spill(&mut self, value: Value<'ctx>, span: Span) -> anyhow::Result<Variable<'ctx>> {
+        let bb = self.builder.get_insert_block().unwrap();
+        let llvm_type = self.llvm_type(&value.ty).unwrap();
+        let pointer = self.build_entry_alloca(bb, llvm_type, "temporary")?;
+        self.builder.build_store(pointer, value.value)?;
+        Ok(Variable {
+            pointer,
+            ty: value.ty,
+            span,
+        })
+    }
+
+    /// An lvalue modified by `operator`, which cannot be an array or a
+    /// function.
+    fn analysis_modifiable_lvalue(
+        &mut self,
+        expr: Expression,
+        operator: Span,
+    ) -> anyhow::Result<Variable<'ctx>> {
+        let span = expr.span();
+        let variable = self.analysis_lvalue(expr, Some(LvalueUse::Assignment(operator)))?;
+        if let Type::Array(..) | Type::Function(..) = variable.ty {
+            anyhow::bail!(SemaError::InvalidAssignment { span, operator });
+        }
+        self.check_complete(&variable.ty, span)?;
+        Ok(variable)
+    }
+
+    /// Check that an object of type `ty` read or written at `span` has a
+    /// known size, unlike a structure that is only declared.
+    fn check_complete(&self, ty: &Type, span: Span) -> anyhow::Result<()> {
+        if !self.is_complete(ty) {
+            anyhow::bail!(SemaError::IncompleteType {
+                name: ty.to_string(),
+                span,
+            });
+        }
+        Ok(())
+    }
+
+    /// Load the value of type `ty` at `pointer`, read at `span`.
+    fn load(
+        &self,
+        pointer: PointerValue<'ctx>,
+        ty: Type,
+        span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        self.check_complete(&ty, span)?;
+        let Some(llvm_type) = self.llvm_type(&ty) else {
+            anyhow::bail!("Load of a `void` object");
+        };
+        let value = self.builder.build_load(llvm_type, pointer, "load")?;
+        Ok(Value {
+            value,
+            ty,
+            null_pointer: false,
+        })
+    }
+
+    /// Lower `=` and compound assignments such as `+=`. The left operand is
+    /// evaluated once, and the result is the value stored.
+    fn analysis_assignment(
+        &mut self,
+        lhs: Expression,
+        bin_op: BinaryOperator,
+        rhs: Expression,
+        span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let lhs_span = lhs.span();
+        let Variable { pointer, ty, .. } = self.analysis_modifiable_lvalue(lhs, span)?;
+        let rhs_span = rhs.span();
+        let Some(rhs) = self.analysis_converted(rhs)? else {
+            anyhow::bail!(SemaError::VoidValue {
+                what: "assigned value",
+                span: rhs_span,
+                operator: Some(span),
+            });
+        };
+        let value = match bin_op.compound_operator() {
+            None => rhs,
+            Some(bin_op) => {
+                let current = self.load(pointer, ty.clone(), lhs_span)?;
+                self.build_binary(current, bin_op, rhs, span, rhs_span)?
+            }
+        };
+        let Some(value) = self.convert(value, &ty)? else {
+            anyhow::bail!(SemaError::IncompatibleType {
+                what: "assignment",
+                span: rhs_span,
+            });
+        };
+        tracing::trace!("assigning {value} to {pointer}");
+        self.builder.build_store(pointer, value)?;
+        Ok(Value {
+            value,
+            ty,
+            null_pointer: false,
+        })
+    }
+
+    /// Lower `++`/`--`. The prefix forms yield the new value, the postfix
+    /// forms the old one.
+    fn analysis_increment(
+        &mut self,
+        operand: Expression,
+        increment: bool,
+        prefix: bool,
+        span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let operand_span = operand.span();
+        let Variable { pointer, ty, .. } = self.analysis_modifiable_lvalue(operand, span)?;
+        let invalid = SemaError::InvalidUnaryOperand {
+            ty: ty.clone(),
+            span: operand_span,
+            operator: span,
+        };
+        if !matches!(ty, Type::Integer(_) | Type::Pointer(_)) {
+            anyhow::bail!(invalid);
+        }
+        let old = self.load(pointer, ty.clone(), operand_span)?;
+        let one = Value::int(
+            self.context.i32_type().const_int(1, false),
+            IntegerType::INT,
+        );
+        let bin_op = if increment {
+            BinaryOperator::Plus
+        } else {
+            BinaryOperator::Minus
+        };
+        let new = self.build_binary(old.clone(), bin_op, one, span, span)?;
+        let Some(new) = self.convert(new, &ty)? else {
+            anyhow::bail!(invalid);
+        };
+        self.builder.build_store(pointer, new)?;
+        Ok(if prefix {
+            Value {
+                value: new,
+                ty,
+                null_pointer: false,
+            }
+        } else {
+            old
+        })
+    }
+
+    /// Warn about shifting by a constant amount that is negative or not less
+    /// than the width of the shifted operand, which is undefined behavior.
+    fn check_shift_count(
+        &mut self,
+        lhs: IntValue<'ctx>,
+        rhs: IntValue<'ctx>,
+        rhs_type: IntegerType,
+        rhs_span: Span,
+    ) -> anyhow::Result<()> {
+        let count = if rhs_type.signed {
+            rhs.get_sign_extended_constant().map(i128::from)
+        } else {
+            rhs.get_zero_extended_constant().map(i128::from)
+        };
+        let Some(count) = count else {
+            return Ok(());
+        };
+        let width = lhs.get_type().get_bit_width();
+        let message = if count < 0 {
+            "shift count is negative"
+        } else if count >= i128::from(width) {
+            "shift count is not less than the width of the type"
+        } else {
+            return Ok(());
+        };
+        self.diagnostics.push(
+            Diagnostic::warning(message)
+                .with_primary(rhs_span, format!("shifting by {count}"))
+                .with_note(format!("the shifted operand is {width} bits wide"))
+                .with_note("the result of this shift is undefined"),
+        );
+        Ok(())
+    }
+
+    /// Lower `condition ? then : otherwise`, evaluating only the selected
+    /// branch. Both branches are either `void`, integers brought to their
+    /// common type, pointers brought to their composite pointer type, or the
+    /// same structure or union.
+    fn analysis_conditional(
+        &mut self,
+        condition: Expression,
+        then: Expression,
+        otherwise: Expression,
+        span: Span,
+    ) -> anyhow::Result<Option<Value<'ctx>>> {
+        let (then_span, otherwise_span) = (then.span(), otherwise.span());
+        let condition = self.analysis_truth_value(condition, span)?;
+        let this_block = self.builder.get_insert_block().unwrap();
+        let end_block = self
+            .context
+            .insert_basic_block_after(this_block, "cond_end");
+        let else_block = self
+            .context
+            .insert_basic_block_after(this_block, "cond_else");
+        let then_block = self
+            .context
+            .insert_basic_block_after(this_block, "cond_then");
+        self.builder
+            .build_conditional_branch(condition, then_block, else_block)?;
+        let (then, then_block) = self.analysis_branch(then, then_block, end_block)?;
+        let (otherwise, else_block) = self.analysis_branch(otherwise, else_block, end_block)?;
+        let (then, otherwise) = match (then, otherwise) {
+            (None, None) => {
+                self.builder.position_at_end(end_block);
+                return Ok(None);
+            }
+            (Some(then), Some(otherwise)) => (then, otherwise),
+            _ => anyhow::bail!(SemaError::ConditionalTypeMismatch {
+                then_span,
+                otherwise_span,
+                operator: span,
+            }),
+        };
+        let ty = match (&then.ty, &otherwise.ty) {
+            (Type::Integer(then_type), Type::Integer(otherwise_type)) => {
+                Type::Integer(common_type(*then_type, *otherwise_type))
+            }
+            (Type::Record(then_type), Type::Record(otherwise_type))
+                if then_type == otherwise_type =>
+            {
+                then.ty.clone()
+            }
+            _ => match composite_pointer_type(&then, &otherwise) {
+                Some(ty) => ty,
+                None => anyhow::bail!(SemaError::ConditionalTypeMismatch {
+                    then_span,
+                    otherwise_span,
+                    operator: span,
+                }),
             },
+        };
+        // convert each value at the end of its branch
+        self.builder
+            .position_before(&then_block.get_terminator().unwrap());
+        let then = self.convert(then, &ty)?.unwrap();
+        self.builder
+            .position_before(&else_block.get_terminator().unwrap());
+        let otherwise = self.convert(otherwise, &ty)?.unwrap();
+        self.builder.position_at_end(end_block);
+        let phi = self.builder.build_phi(then.get_type(), "cond")?;
+        phi.add_incoming(&[(&then, then_block), (&otherwise, else_block)]);
+        Ok(Some(Value {
+            value: phi.as_basic_value(),
+            ty,
+            null_pointer: false,
+        }))
+    }
+
+    /// Emit a branch of `?:` into `block`. Returns its value and the block it
+    /// ends in.
+    fn analysis_branch(
+        &mut self,
+        expr: Expression,
+        block: BasicBlock<'ctx>,
+        end_block: BasicBlock<'ctx>,
+    ) -> anyhow::Result<(Option<Value<'ctx>>, BasicBlock<'ctx>)> {
+        self.builder.position_at_end(block);
+        let value = self.analysis_converted(expr)?;
+        let block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(end_block)?;
+        Ok((value, block))
+    }
+
+    /// Compare an operand of `&&`/`||` against zero.
+    fn analysis_truth_value(
+        &mut self,
+        expr: Expression,
+        operator: Span,
+    ) -> anyhow::Result<IntValue<'ctx>> {
+        let span = expr.span();
+        let value = self.analysis_operand(expr, operator)?;
+        self.scalar_truth_value(value, span)
+    }
+
+    /// Lower `lhs && rhs` and `lhs || rhs` so that `rhs` is only evaluated when
+    /// `lhs` does not decide the result. Yields an `int` 0 or 1.
+    fn analysis_logical(
+        &mut self,
+        lhs: Expression,
+        bin_op: BinaryOperator,
+        rhs: Expression,
+        span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let (name, short_circuit) = match bin_op {
+            BinaryOperator::LogicalAnd => ("and", false),
+            BinaryOperator::LogicalOr => ("or", true),
+            _ => unreachable!(),
+        };
+        let lhs = self.analysis_truth_value(lhs, span)?;
+        let lhs_block = self.builder.get_insert_block().unwrap();
+        let end_block = self
+            .context
+            .insert_basic_block_after(lhs_block, &format!("{name}_end"));
+        let rhs_block = self
+            .context
+            .insert_basic_block_after(lhs_block, &format!("{name}_rhs"));
+        if short_circuit {
+            self.builder
+                .build_conditional_branch(lhs, end_block, rhs_block)?;
+        } else {
+            self.builder
+                .build_conditional_branch(lhs, rhs_block, end_block)?;
+        }
+        self.builder.position_at_end(rhs_block);
+        let rhs = self.analysis_truth_value(rhs, span)?;
+        let rhs_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(end_block)?;
+        self.builder.position_at_end(end_block);
+        let bool_type = self.context.bool_type();
+        let phi = self.builder.build_phi(bool_type, name)?;
+        phi.add_incoming(&[
+            (&bool_type.const_int(short_circuit as u64, false), lhs_block),
+            (&rhs, rhs_block),
+        ]);
+        self.bool_to_int(phi.as_basic_value().into_int_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{has_lines, lower, SemaError};
+
+    #[test]
+    fn pointer_arithmetic_scales_by_the_pointee() {
+        let ir = lower(
+            "int *f(int *p, long n) { return p + n; }\n\
+             long g(int *p, int *q) { return p - q; }",
+        )
+        .unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "define ptr @f(ptr %0, i64 %1)",
+                    "%ptr_add = getelementptr inbounds i32, ptr %load, i64 %load1",
+                    "define i64 @g(ptr %0, ptr %1)",
+                    "%ptr_diff = sdiv exact i64",
+                ]
+            ),
+            "{ir}"
+        );
+    }
+
+    #[test]
+    fn incomplete_objects_are_neither_loaded_nor_stored() {
+        for statement in ["*p;", "*p = *q;", "take(*p);"] {
+            let code = format!(
+                "struct S; struct S *p, *q; void take(struct S);\n\
+                 void f(void) {{ {statement} }}"
+            );
+            let err = lower(&code).unwrap_err();
+            let SemaError::IncompleteType { name, span } = &err else {
+                panic!("{statement}: {err:?}");
+            };
+            assert_eq!(name, "struct S");
+            assert_eq!(&code[span.start..span.end], "*p", "{statement}");
+        }
+    }
+
+    #[test]
+    fn calls_through_function_pointers() {
+        let ir = lower(
+            "int g(int x) { return x; }\n\
+             int f(int (*fp)(int)) { return fp(2); }\n\
+             int h(void) { return f(g) + (*f)(&g); }",
+        )
+        .unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "define i32 @f(ptr %0)",
+                    "%call = call i32 %load(i32 2)",
+                    "define i32 @h()",
+                    "%call = call i32 @f(ptr @g)",
+                    "%call1 = call i32 @f(ptr @g)",
+                ]
+            ),
+            "{ir}"
+        );
+    }
+
+    #[test]
+    fn struct_operands_are_user_errors() {
+        let prelude = "struct S { int x; } s; char c;\n";
+        let cases = [
+            ("s + 1", "+"),
+            ("c << s", "<<"),
+            ("s++", "s"),
+            ("--s", "s"),
+            ("!s", "s"),
+            ("s ? 1 : 2", "s"),
+            ("c && s", "s"),
+        ];
+        for (expr, expected) in cases {
+            let code = format!("{prelude}int f(void) {{ return {expr}; }}");
+            let span = match lower(&code).unwrap_err() {
+                SemaError::InvalidOperands { lhs, rhs, span } => {
+                    assert!(lhs.to_string() == "struct S" || rhs.to_string() == "struct S");
+                    span
+                }
+                SemaError::InvalidUnaryOperand { ty, span, .. }
+                | SemaError::NotScalar { ty, span } => {
+                    assert_eq!(ty.to_string(), "struct S");
+                    span
+                }
+                err => panic!("{expr}: {err:?}"),
+            };
+            assert_eq!(&code[span.start..span.end], expected, "{expr}");
         }
     }
 }
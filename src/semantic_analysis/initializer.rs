@@ -0,0 +1,515 @@
+use std::collections::{HashMap, VecDeque};
+
+use inkwell::{
+    types::{BasicType, BasicTypeEnum},
+    values::{ArrayValue, BasicValue, BasicValueEnum, PointerValue},
+};
+
+use crate::{
+    diagnostics::{Diagnostic, Span},
+    syntax_analysis::{
+        expression::{Expression, PrefixUnaryOperator, RefOrCall},
+        initializer::Initializer,
+        types::{ArrayLength, RecordKind, Type},
+    },
+};
+
+use super::{constant::IntegerConstant, types::converts, SemaError, IR};
+
+/// A scalar in an initializer, and the element of the initialized object it
+/// initializes.
+#[derive(Debug)]
+pub(super) struct ScalarInitializer {
+    /// Indices of the element, from the outermost array or record.
+    path: Vec<u64>,
+    ty: Type,
+    value: Expression,
+}
+
+/// Arrays, structures and unions, which are initialized element by element.
+fn is_aggregate(ty: &Type) -> bool {
+    matches!(ty, Type::Array(..) | Type::Record(_))
+}
+
+impl<'ctx> IR<'ctx> {
+    /// Number of scalars in an object of type `ty`. Only the first member of
+    /// a union is initialized.
+    fn scalar_count(&self, ty: &Type) -> u64 {
+        match ty {
+            Type::Array(element, ArrayLength::Known(length)) => length * self.scalar_count(element),
+            Type::Array(..) => 0,
+            Type::Record(_) => (0..)
+                .map_while(|position| self.initialized_element(ty, position))
+                .map(|member| self.scalar_count(&member))
+                .sum(),
+            _ => 1,
+        }
+    }
+
+    /// Type of the element at `index` of the aggregate type `ty` that an
+    /// initializer can initialize, if there is one.
+    fn initialized_element(&self, ty: &Type, index: u64) -> Option<Type> {
+        match ty {
+            Type::Array(_, ArrayLength::Known(length)) if index >= *length => None,
+            Type::Array(element, _) => Some((**element).clone()),
+            Type::Record(record) if record.kind == RecordKind::Union && index > 0 => None,
+            Type::Record(record) => self.member_type(record, index as usize),
+            _ => None,
+        }
+    }
+
+    /// Match the scalars of `init` with the elements of an object of type
+    /// `ty` that they initialize, in the order they are written. An array of
+    /// unknown length gets as many elements as are initialized.
+    pub(super) fn designate_initializer(
+        &mut self,
+        ty: Type,
+        init: Initializer,
+    ) -> anyhow::Result<(Type, Vec<ScalarInitializer>)> {
+        let mut scalars = Vec::new();
+        let length = match init {
+            Initializer::Expression(value) if matches!(ty, Type::Array(..)) => {
+                anyhow::bail!(SemaError::IncompatibleType {
+                    what: "initializer",
+                    span: value.span(),
+                });
+            }
+            Initializer::Expression(value) => {
+                scalars.push(ScalarInitializer {
+                    path: Vec::new(),
+                    ty: ty.clone(),
+                    value,
+                });
+                1
+            }
+            Initializer::List(items, span) => {
+                let length = self.designate_braced(&ty, items, &mut Vec::new(), &mut scalars)?;
+                if length == 0 && matches!(ty, Type::Array(_, ArrayLength::Unknown)) {
+                    anyhow::bail!(SemaError::InvalidArrayLength { span });
+                }
+                length
+            }
+        };
+        let ty = match ty {
+            Type::Array(element, ArrayLength::Unknown) => {
+                Type::Array(element, ArrayLength::Known(length))
+            }
+            ty => ty,
+        };
+        Ok((ty, scalars))
+    }
+
+    /// Designate the items in the braces of an initializer for an object of
+    /// type `ty` at `path`. Returns the number of elements initialized.
+    fn designate_braced(
+        &mut self,
+        ty: &Type,
+        items: Vec<Initializer>,
+        path: &mut Vec<u64>,
+        scalars: &mut Vec<ScalarInitializer>,
+    ) -> anyhow::Result<u64> {
+        let mut items = VecDeque::from(items);
+        let length = match ty {
+            ty if is_aggregate(ty) => self.designate_elements(ty, &mut items, path, scalars)?,
+            // a scalar in braces
+            _ => match items.pop_front() {
+                Some(Initializer::List(items, _)) => {
+                    self.designate_braced(ty, items, path, scalars)?
+                }
+                Some(Initializer::Expression(value)) => {
+                    self.designate_scalar(ty.clone(), value, path, scalars);
+                    1
+                }
+                None => 0,
+            },
+        };
+        if let Some(excess) = items.pop_front() {
+            self.diagnostics.push(
+                Diagnostic::warning("excess elements in initializer")
+                    .with_primary(excess.span(), "ignored")
+                    .with_note(format!("the initialized object has type `{ty}`")),
+            );
+        }
+        Ok(length)
+    }
+
+    /// Designate items for the elements of the aggregate type `ty` at `path`,
+    /// until either runs out. Where an element is an aggregate itself and its
+    /// braces are left out, it takes as many items as it has scalars. An
+    /// expression initializes a whole structure or union element if it has
+    /// its type, and its first scalar otherwise.
+    fn designate_elements(
+        &mut self,
+        ty: &Type,
+        items: &mut VecDeque<Initializer>,
+        path: &mut Vec<u64>,
+        scalars: &mut Vec<ScalarInitializer>,
+    ) -> anyhow::Result<u64> {
+        let mut index = 0;
+        while let Some(element) = self.initialized_element(ty, index) {
+            let Some(item) = items.pop_front() else {
+                break;
+            };
+            path.push(index);
+            match item {
+                Initializer::List(inner, _) => {
+                    self.designate_braced(&element, inner, path, scalars)?;
+                }
+                Initializer::Expression(value)
+                    if matches!(element, Type::Record(_))
+                        && self.value_type(&value)? == element =>
+                {
+                    self.designate_scalar(element, value, path, scalars);
+                }
+                item if is_aggregate(&element) => {
+                    items.push_front(item);
+                    self.designate_elements(&element, items, path, scalars)?;
+                }
+                Initializer::Expression(value) => {
+                    self.designate_scalar(element, value, path, scalars)
+                }
+            }
+            path.pop();
+            index += 1;
+        }
+        Ok(index)
+    }
+
+    /// Designate `value` for the element of type `ty` at `path`.
+    fn designate_scalar(
+        &self,
+        ty: Type,
+        value: Expression,
+        path: &[u64],
+        scalars: &mut Vec<ScalarInitializer>,
+    ) {
+        scalars.push(ScalarInitializer {
+            path: path.to_vec(),
+            ty,
+            value,
+        });
+    }
+
+    /// Store the initial value of an object of type `ty` at `pointer`.
+    /// Elements without an initializer are zero.
+    pub(super) fn initialize(
+        &mut self,
+        pointer: PointerValue<'ctx>,
+        ty: &Type,
+        scalars: Vec<ScalarInitializer>,
+    ) -> anyhow::Result<()> {
+        let Some(llvm_type) = self.llvm_type(ty) else {
+            anyhow::bail!("Initialization of a `void` object");
+        };
+        if (scalars.len() as u64) < self.scalar_count(ty) {
+            let align = pointer
+                .as_instruction()
+                .and_then(|alloca| alloca.get_alignment().ok())
+                .unwrap_or(1);
+            let zero = self.context.i8_type().const_zero();
+            let size = llvm_type.size_of().unwrap();
+            self.builder.build_memset(pointer, align, zero, size)?;
+        }
+        for ScalarInitializer {
+            path,
+            ty: scalar_type,
+            value,
+        } in scalars
+        {
+            let value_span = value.span();
+            let Some(value) = self.analysis_converted(value)? else {
+                anyhow::bail!(SemaError::VoidValue {
+                    what: "initializer",
+                    span: value_span,
+                    operator: None,
+                });
+            };
+            let Some(value) = self.convert(value, &scalar_type)? else {
+                anyhow::bail!(SemaError::IncompatibleType {
+                    what: "initializer",
+                    span: value_span,
+                });
+            };
+            let element = self.element_pointer(pointer, ty.clone(), &path)?;
+            tracing::trace!("initializing {element} with {value}");
+            self.builder.build_store(element, value)?;
+        }
+        Ok(())
+    }
+
+    /// Address of the element at `path` in the object of type `ty` at
+    /// `pointer`.
+    fn element_pointer(
+        &self,
+        mut pointer: PointerValue<'ctx>,
+        mut ty: Type,
+        path: &[u64],
+    ) -> anyhow::Result<PointerValue<'ctx>> {
+        let i64_type = self.context.i64_type();
+        for &index in path {
+            (pointer, ty) = match ty {
+                Type::Array(element, _) => {
+                    let llvm_type = self.llvm_type(&element).unwrap();
+                    let index = i64_type.const_int(index, false);
+                    // SAFETY: the path of an initializer is within the object
+                    let element_pointer = unsafe {
+                        self.builder
+                            .build_in_bounds_gep(llvm_type, pointer, &[index], "element")?
+                    };
+                    (element_pointer, *element)
+                }
+                Type::Record(record) => self.member_pointer(pointer, &record, index as usize)?,
+                _ => unreachable!("only aggregates have elements"),
+            };
+        }
+        Ok(pointer)
+    }
+
+    /// Initial value of a file-scope object of type `ty`, initialized at
+    /// `span`, which must be constant. Elements without an initializer are
+    /// zero.
+    pub(super) fn constant_initializer(
+        &mut self,
+        ty: &Type,
+        scalars: Vec<ScalarInitializer>,
+        span: Span,
+    ) -> anyhow::Result<BasicValueEnum<'ctx>> {
+        let mut values = HashMap::new();
+        for ScalarInitializer {
+            path,
+            ty: scalar_type,
+            value,
+        } in scalars
+        {
+            let value = self.constant_scalar(&scalar_type, value)?;
+            values.insert(path, value);
+        }
+        self.constant_object(ty, &mut Vec::new(), &values, span)
+    }
+
+    /// Constant value of the scalar initializer `value` of an element of
+    /// type `ty`: an integer constant expression, a null pointer constant,
+    /// or the address of an object or function at file scope.
+    fn constant_scalar(
+        &mut self,
+        ty: &Type,
+        value: Expression,
+    ) -> anyhow::Result<BasicValueEnum<'ctx>> {
+        let span = value.span();
+        let value_type = self.value_type(&value)?;
+        if value_type == Type::Void {
+            anyhow::bail!(SemaError::VoidValue {
+                what: "initializer",
+                span,
+                operator: None,
+            });
+        }
+        let null_pointer = self.is_null_pointer_constant(&value);
+        if !converts(&value_type, ty, null_pointer) {
+            anyhow::bail!(SemaError::IncompatibleType {
+                what: "initializer",
+                span,
+            });
+        }
+        let not_constant = || SemaError::NonConstantInitializer { span };
+        let constant = match (&value_type, ty) {
+            (Type::Integer(_), Type::Integer(to)) => {
+                let constant = self.constant_int(&value).map_err(|_| not_constant())?;
+                IntegerConstant::new(constant.value, *to)
+            }
+            (_, Type::Pointer(_)) if null_pointer => {
+                return Ok(self.llvm_type(ty).unwrap().const_zero());
+            }
+            (Type::Pointer(_), Type::Pointer(_)) => {
+                let address = self.address_constant(&value).ok_or_else(not_constant)?;
+                return Ok(address.as_basic_value_enum());
+            }
+            // an address is never null, so it converts to a `_Bool` 1
+            (Type::Pointer(_), Type::Integer(to)) => {
+                self.address_constant(&value).ok_or_else(not_constant)?;
+                IntegerConstant::new(1, *to)
+            }
+            _ => anyhow::bail!(not_constant()),
+        };
+        Ok(self.constant_value(constant).value)
+    }
+
+    /// Address of the object or function at file scope that `expr` points to,
+    /// if it is an address constant such as `&x`, `array` or `(char *)f`.
+    fn address_constant(&self, expr: &Expression) -> Option<PointerValue<'ctx>> {
+        match expr {
+            Expression::Paren(expr, _) | Expression::Evaluate(expr) => self.address_constant(expr),
+            Expression::Cast(_, operand, _) => self.address_constant(operand),
+            Expression::PrefixUnary(PrefixUnaryOperator::AddressOf, operand, _) => {
+                self.global_designator(operand).map(|(pointer, _)| pointer)
+            }
+            // arrays and functions decay to pointers
+            expr => match self.global_designator(expr)? {
+                (pointer, Type::Array(..) | Type::Function(..)) => Some(pointer),
+                _ => None,
+            },
+        }
+    }
+
+    /// Address and type of the object or function at file scope that `expr`
+    /// names, if it does.
+    fn global_designator(&self, expr: &Expression) -> Option<(PointerValue<'ctx>, Type)> {
+        match expr {
+            Expression::Paren(expr, _) => self.global_designator(expr),
+            Expression::RefOrCall(RefOrCall::Variable(name, span))
+                if self.enum_constant(name).is_none() =>
+            {
+                let variable = self.variable(name.clone(), *span).ok()?;
+                Some((variable.pointer, variable.ty))
+            }
+            _ => None,
+        }
+    }
+
+    /// Constant value of the element of type `ty` at `path` in an object,
+    /// initialized at `span`, whose scalars at their paths have the constant
+    /// `values`.
+    fn constant_object(
+        &self,
+        ty: &Type,
+        path: &mut Vec<u64>,
+        values: &HashMap<Vec<u64>, BasicValueEnum<'ctx>>,
+        span: Span,
+    ) -> anyhow::Result<BasicValueEnum<'ctx>> {
+        let llvm_type = self.llvm_type(ty).unwrap();
+        if let Some(value) = values.get(path) {
+            return Ok(*value);
+        }
+        if !values
+            .keys()
+            .any(|initialized| initialized.starts_with(path))
+        {
+            return Ok(llvm_type.const_zero());
+        }
+        let mut element_value = |ir: &Self, index: u64, ty: &Type| {
+            path.push(index);
+            let value = ir.constant_object(ty, path, values, span);
+            path.pop();
+            value
+        };
+        Ok(match ty {
+            Type::Array(element, ArrayLength::Known(length)) => {
+                let elements = (0..*length)
+                    .map(|index| element_value(self, index, element))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let element_type = self.llvm_type(element).unwrap();
+                const_array(element_type, &elements).as_basic_value_enum()
+            }
+            Type::Record(record) if record.kind == RecordKind::Union => {
+                // only the first member is initialized, and the LLVM type of
+                // the union has a field of its most aligned member
+                let struct_type = llvm_type.into_struct_type();
+                let member = self.member_type(record, 0).unwrap();
+                let value = element_value(self, 0, &member)?;
+                if Some(value.get_type()) != struct_type.get_field_type_at_index(0) {
+                    anyhow::bail!(SemaError::Unsupported {
+                        feature: "constant initialization of this union member",
+                        span,
+                    });
+                }
+                let mut fields = vec![value];
+                if let Some(padding) = struct_type.get_field_type_at_index(1) {
+                    fields.push(padding.const_zero());
+                }
+                struct_type
+                    .const_named_struct(&fields)
+                    .as_basic_value_enum()
+            }
+            Type::Record(record) => {
+                let fields = (0..)
+                    .map_while(|position| self.member_type(record, position))
+                    .enumerate()
+                    .map(|(index, member)| element_value(self, index as u64, &member))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                llvm_type
+                    .into_struct_type()
+                    .const_named_struct(&fields)
+                    .as_basic_value_enum()
+            }
+            _ => unreachable!("`{ty}` is a scalar without an initializer"),
+        })
+    }
+}
+
+/// A constant array of `values`, which have the type `element_type`.
+fn const_array<'ctx>(
+    element_type: BasicTypeEnum<'ctx>,
+    values: &[BasicValueEnum<'ctx>],
+) -> ArrayValue<'ctx> {
+    match element_type {
+        BasicTypeEnum::IntType(ty) => {
+            let values: Vec<_> = values.iter().map(|value| value.into_int_value()).collect();
+            ty.const_array(&values)
+        }
+        BasicTypeEnum::PointerType(ty) => {
+            let values: Vec<_> = values
+                .iter()
+                .map(|value| value.into_pointer_value())
+                .collect();
+            ty.const_array(&values)
+        }
+        BasicTypeEnum::ArrayType(ty) => {
+            let values: Vec<_> = values
+                .iter()
+                .map(|value| value.into_array_value())
+                .collect();
+            ty.const_array(&values)
+        }
+        BasicTypeEnum::StructType(ty) => {
+            let values: Vec<_> = values
+                .iter()
+                .map(|value| value.into_struct_value())
+                .collect();
+            ty.const_array(&values)
+        }
+        BasicTypeEnum::FloatType(_) | BasicTypeEnum::VectorType(_) => {
+            unreachable!("there are no floating or vector types")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{has_lines, lower};
+
+    #[test]
+    fn array_initializer_zeroes_the_rest() {
+        let ir = lower("int f(void) { int a[4] = {1, 2}; return a[3]; }").unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "%a = alloca [4 x i32]",
+                    "call void @llvm.memset",
+                    "%element = getelementptr inbounds i32, ptr %a, i64 0",
+                    "store i32 1, ptr %element",
+                    "%element1 = getelementptr inbounds i32, ptr %a, i64 1",
+                    "store i32 2, ptr %element1",
+                    "%ptr_add = getelementptr inbounds i32, ptr %a, i64 3",
+                ]
+            ),
+            "{ir}"
+        );
+    }
+
+    #[test]
+    fn tentative_definitions_are_zeroed() {
+        let ir = lower("int x; int y; struct s { int a; char b; } s; int y = 3; int y;").unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "@x = global i32 0",
+                    "@y = global i32 3",
+                    "@s = global %struct.s zeroinitializer",
+                ]
+            ),
+            "{ir}"
+        );
+    }
+}
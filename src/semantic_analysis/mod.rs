@@ -1,8 +1,14 @@
+mod constant;
 mod declaration;
+mod enumeration;
+mod error;
 mod expression;
+mod initializer;
+mod record;
 mod statement;
+mod types;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::anyhow;
 use inkwell::{
@@ -10,11 +16,20 @@ use inkwell::{
     module::Module, values::PointerValue,
 };
 
-use crate::syntax_analysis::{
-    block_statement::{Block, BlockInner},
-    translation_unit::TranslationUnit,
+use crate::{
+    diagnostics::{Diagnostic, Span},
+    syntax_analysis::{
+        block_statement::{Block, BlockInner},
+        translation_unit::TranslationUnit,
+        types::{IntegerType, Type},
+    },
 };
 
+use constant::IntegerConstant;
+use enumeration::EnumConstant;
+pub use error::SemaError;
+use record::Record;
+
 fn remove_dead_code(bb: &BasicBlock) {
     let mut dead_code = false;
     for inst in bb.get_instructions() {
@@ -28,12 +43,102 @@ fn remove_dead_code(bb: &BasicBlock) {
     }
 }
 
+/// A variable in scope.
+#[derive(Debug, Clone)]
+struct Variable<'ctx> {
+    pointer: PointerValue<'ctx>,
+    ty: Type,
+    span: Span,
+}
+
+/// What a structure, union or enumeration tag declares.
+#[derive(Debug, Clone, Copy)]
+enum Tag {
+    /// Index of a structure or union in `IR::records`.
+    Record(usize),
+    Enum {
+        underlying: IntegerType,
+        /// Whether the underlying type was written, as in `enum e : long`.
+        fixed: bool,
+        /// Whether the enumerators have been listed.
+        defined: bool,
+        span: Span,
+    },
+}
+
+/// C types of the return value and parameters of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Signature {
+    return_type: Type,
+    params: Vec<Type>,
+}
+
+/// Where `break` and `continue` jump to from the body of a loop or switch.
+struct JumpTargets<'ctx> {
+    break_block: BasicBlock<'ctx>,
+    /// `None` for a switch, whose `continue` targets the enclosing loop.
+    continue_block: Option<BasicBlock<'ctx>>,
+}
+
+/// Labels found so far in the body of a switch.
+struct SwitchLabels<'ctx> {
+    /// Type of the controlling expression, after integer promotion.
+    ty: IntegerType,
+    /// Value converted to `ty`, target and span of each `case` label.
+    cases: Vec<(IntegerConstant, BasicBlock<'ctx>, Span)>,
+    default: Option<(BasicBlock<'ctx>, Span)>,
+}
+
+/// A label in the function being lowered.
+struct Label<'ctx> {
+    block: BasicBlock<'ctx>,
+    /// Where the label is defined, once it has been found.
+    definition: Option<Span>,
+    /// The first `goto` to the label, if any.
+    first_use: Option<Span>,
+}
+
 struct IR<'ctx> {
     context: &'ctx Context,
     module: Module<'ctx>,
     builder: Builder<'ctx>,
-    local_variables: HashMap<String, PointerValue<'ctx>>,
+    local_variables: HashMap<String, Variable<'ctx>>,
+    global_variables: HashMap<String, Variable<'ctx>>,
+    /// Enumeration constants, which share the namespace of variables. A name
+    /// is in at most one of `local_variables` and `local_enumerators`.
+    local_enumerators: HashMap<String, EnumConstant>,
+    global_enumerators: HashMap<String, EnumConstant>,
+    /// Types named by typedef names.
+    local_typedefs: HashMap<String, Type>,
+    global_typedefs: HashMap<String, Type>,
+    /// Local variables, enumerators and typedef names declared in the
+    /// innermost scope.
+    scope_variables: HashSet<String>,
+    /// Structures and unions, indexed by `RecordType::id`.
+    records: Vec<Record<'ctx>>,
+    /// Structure, union and enumeration tags, which have a namespace of
+    /// their own.
+    local_tags: HashMap<String, Tag>,
+    global_tags: HashMap<String, Tag>,
+    /// Tags declared in the innermost scope.
+    scope_tags: HashSet<String>,
+    /// Types defined by structure, union and enumeration specifiers, by the
+    /// span of the specifier, which the declarators of a declaration share.
+    defined_types: HashMap<Span, Type>,
     return_value: Option<PointerValue<'ctx>>,
+    /// Return type of the function being lowered.
+    return_type: Type,
+    /// Enclosing loops and switches, innermost last.
+    jump_targets: Vec<JumpTargets<'ctx>>,
+    /// Enclosing switches, innermost last.
+    switches: Vec<SwitchLabels<'ctx>>,
+    /// Labels of the current function, including those only used so far.
+    labels: HashMap<String, Label<'ctx>>,
+    /// Where each function was declared, or defined if it has a body.
+    function_spans: HashMap<String, Span>,
+    functions: HashMap<String, Signature>,
+    /// Warnings found so far. Errors are returned instead.
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'ctx> IR<'ctx> {
@@ -45,7 +150,71 @@ impl<'ctx> IR<'ctx> {
             context,
             module,
             local_variables: HashMap::new(),
+            global_variables: HashMap::new(),
+            local_enumerators: HashMap::new(),
+            global_enumerators: HashMap::new(),
+            local_typedefs: HashMap::new(),
+            global_typedefs: HashMap::new(),
+            scope_variables: HashSet::new(),
+            records: Vec::new(),
+            local_tags: HashMap::new(),
+            global_tags: HashMap::new(),
+            scope_tags: HashSet::new(),
+            defined_types: HashMap::new(),
             return_value: None,
+            return_type: Type::Void,
+            jump_targets: Vec::new(),
+            switches: Vec::new(),
+            labels: HashMap::new(),
+            function_spans: HashMap::new(),
+            functions: HashMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Run `f` in a new scope. Variables, enumerators, typedef names and tags
+    /// declared in it may shadow outer ones, and go out of scope afterwards.
+    fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let local_variables = self.local_variables.clone();
+        let local_enumerators = self.local_enumerators.clone();
+        let local_typedefs = self.local_typedefs.clone();
+        let scope_variables = std::mem::take(&mut self.scope_variables);
+        let local_tags = self.local_tags.clone();
+        let scope_tags = std::mem::take(&mut self.scope_tags);
+        let result = f(self);
+        self.local_variables = local_variables;
+        self.local_enumerators = local_enumerators;
+        self.local_typedefs = local_typedefs;
+        self.scope_variables = scope_variables;
+        self.local_tags = local_tags;
+        self.scope_tags = scope_tags;
+        result
+    }
+
+    /// The declaration of `tag` in the current scope, or with `visible`, the
+    /// innermost declaration of it in any enclosing scope.
+    fn find_tag(&self, tag: &str, visible: bool) -> Option<Tag> {
+        if self.builder.get_insert_block().is_none() {
+            self.global_tags.get(tag).copied()
+        } else if visible {
+            self.local_tags
+                .get(tag)
+                .or_else(|| self.global_tags.get(tag))
+                .copied()
+        } else if self.scope_tags.contains(tag) {
+            self.local_tags.get(tag).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Declare `tag` in the current scope.
+    fn insert_tag(&mut self, tag: String, declaration: Tag) {
+        if self.builder.get_insert_block().is_some() {
+            self.scope_tags.insert(tag.to_owned());
+            self.local_tags.insert(tag, declaration);
+        } else {
+            self.global_tags.insert(tag, declaration);
         }
     }
 
@@ -68,6 +237,17 @@ impl<'ctx> IR<'ctx> {
         for decl in decls {
             self.analysis_declaration(decl)?;
         }
+        // tentative definitions that were never initialized define their
+        // objects as zero
+        for (name, variable) in &self.global_variables {
+            let Some(global) = self.module.get_global(name) else {
+                anyhow::bail!("global variable `{name}` is not in the module");
+            };
+            if global.get_initializer().is_none() {
+                let llvm_type = self.llvm_type(&variable.ty).unwrap();
+                global.set_initializer(&llvm_type.const_zero());
+            }
+        }
         self.module
             .verify()
             .map_err(|llvm_err| anyhow!(llvm_err.to_string()))?;
@@ -75,15 +255,32 @@ impl<'ctx> IR<'ctx> {
     }
 }
 
-pub fn analysis(unit: TranslationUnit) -> anyhow::Result<MemoryBuffer> {
+/// Generate LLVM bitcode for a translation unit, along with the warnings found.
+///
+/// Any error other than a [`SemaError`] is a bug in the compiler, and is
+/// reported as [`SemaError::Internal`], optionally dumping the module built so
+/// far.
+pub fn analysis(
+    unit: TranslationUnit,
+    dump_on_ice: bool,
+) -> Result<(MemoryBuffer, Vec<Diagnostic>), SemaError> {
     let context = Context::create();
     let mut ir = IR::new(&context);
     if let Err(err) = ir.analysis_translation_unit(unit) {
-        tracing::error!("Internal error: {err}");
-        tracing::error!("Dump module: {}", ir.module.print_to_string().to_string());
-        anyhow::bail!("Internal error");
+        let err = match err.downcast::<SemaError>() {
+            Ok(err) => return Err(err),
+            Err(err) => err,
+        };
+        tracing::error!("Internal error: {err:?}");
+        if dump_on_ice {
+            eprintln!("Dump module: {}", ir.module.print_to_string().to_string());
+        }
+        return Err(SemaError::Internal {
+            message: err.to_string(),
+            dumped: dump_on_ice,
+        });
     }
-    Ok(ir.module.write_bitcode_to_memory())
+    Ok((ir.module.write_bitcode_to_memory(), ir.diagnostics))
 }
 
 pub fn bitcode_to_string(bitcode: MemoryBuffer) -> anyhow::Result<String> {
@@ -93,3 +290,22 @@ pub fn bitcode_to_string(bitcode: MemoryBuffer) -> anyhow::Result<String> {
         .map_err(|llvm_string| anyhow!(llvm_string.to_string()))?;
     Ok(module.print_to_string().to_string())
 }
+
+/// The LLVM IR generated for `code`, or the semantic error in it.
+#[cfg(test)]
+fn lower(code: &str) -> Result<String, SemaError> {
+    let tokens = crate::lexical_analysis::extract_tokens(code).unwrap();
+    let unit = crate::syntax_analysis::parse(&tokens).unwrap();
+    let (bitcode, _) = analysis(unit, false)?;
+    Ok(bitcode_to_string(bitcode).unwrap())
+}
+
+/// Whether `ir` has lines starting with each of `expected`, in order,
+/// ignoring indentation.
+#[cfg(test)]
+fn has_lines(ir: &str, expected: &[&str]) -> bool {
+    let mut lines = ir.lines().map(str::trim);
+    expected
+        .iter()
+        .all(|expected| lines.any(|line| line.starts_with(expected)))
+}
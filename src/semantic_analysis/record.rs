@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use inkwell::{
+    types::{BasicType, StructType},
+    values::PointerValue,
+};
+
+use crate::{
+    diagnostics::Span,
+    syntax_analysis::types::{Member, RecordKind, RecordSpecifier, RecordType, Type},
+};
+
+use super::{SemaError, Tag, IR};
+
+/// A structure or union in the type table.
+pub(super) struct Record<'ctx> {
+    kind: RecordKind,
+    llvm_type: StructType<'ctx>,
+    /// `None` until the record is defined.
+    members: Option<Vec<RecordMember>>,
+    size: u64,
+    align: u64,
+    /// Where the record was defined, or first declared.
+    span: Span,
+}
+
+struct RecordMember {
+    name: Option<String>,
+    ty: Type,
+    span: Span,
+}
+
+fn align_to(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align) * align
+}
+
+impl<'ctx> IR<'ctx> {
+    /// Resolve a structure or union specifier to its type. A tag refers to
+    /// the visible declaration of it, unless the specifier defines it or is
+    /// the whole of a `declaration`; then it refers to the declaration in the
+    /// current scope, which is created if there is none.
+    pub(super) fn resolve_record(
+        &mut self,
+        specifier: RecordSpecifier,
+        declaration: bool,
+    ) -> anyhow::Result<RecordType> {
+        let RecordSpecifier {
+            kind,
+            tag,
+            members,
+            span,
+        } = specifier;
+        let visible = members.is_none() && !declaration;
+        let existing = tag.as_deref().and_then(|tag| self.find_tag(tag, visible));
+        let id = match existing {
+            Some(Tag::Enum { span: previous, .. }) => anyhow::bail!(SemaError::TagKindMismatch {
+                tag: tag.unwrap(),
+                span,
+                previous,
+            }),
+            Some(Tag::Record(id)) => {
+                let record = &self.records[id];
+                let tag = tag.to_owned().unwrap();
+                if record.kind != kind {
+                    anyhow::bail!(SemaError::TagKindMismatch {
+                        tag,
+                        span,
+                        previous: record.span,
+                    });
+                }
+                if members.is_some() && record.members.is_some() {
+                    anyhow::bail!(SemaError::Redefinition {
+                        name: format!("{kind} {tag}"),
+                        span,
+                        previous: Some(record.span),
+                    });
+                }
+                id
+            }
+            None => self.declare_record(kind, tag.to_owned(), span),
+        };
+        if let Some(members) = members {
+            self.records[id].span = span;
+            self.define_record(id, members)?;
+        }
+        Ok(RecordType { kind, tag, id })
+    }
+
+    /// Add an incomplete record to the type table, and its tag to the
+    /// current scope.
+    fn declare_record(&mut self, kind: RecordKind, tag: Option<String>, span: Span) -> usize {
+        let id = self.records.len();
+        let name = format!("{kind}.{}", tag.as_deref().unwrap_or("anon"));
+        self.records.push(Record {
+            kind,
+            llvm_type: self.context.opaque_struct_type(&name),
+            members: None,
+            size: 0,
+            align: 1,
+            span,
+        });
+        if let Some(tag) = tag {
+            self.insert_tag(tag, Tag::Record(id));
+        }
+        id
+    }
+
+    /// Complete a record with its members, and lay them out as the C ABI
+    /// does: each struct member at the next offset aligned for it, and every
+    /// union member at offset 0.
+    fn define_record(&mut self, id: usize, members: Vec<Member>) -> anyhow::Result<()> {
+        let mut resolved = Vec::new();
+        for Member(ty, name, span) in members {
+            let ty = self.resolve_type(ty, span)?;
+            if !self.is_complete(&ty) {
+                anyhow::bail!(SemaError::IncompleteType {
+                    name: name.unwrap_or_default(),
+                    span,
+                });
+            }
+            resolved.push(RecordMember { name, ty, span });
+        }
+        let mut names = HashMap::new();
+        for (name, span) in self.member_names(&resolved) {
+            if let Some(previous) = names.insert(name.to_owned(), span) {
+                anyhow::bail!(SemaError::DuplicateMember {
+                    name,
+                    span,
+                    previous,
+                });
+            }
+        }
+        let kind = self.records[id].kind;
+        let (mut size, mut align) = (0, 1);
+        for member in &resolved {
+            let (member_size, member_align) = self.size_align(&member.ty);
+            align = align.max(member_align);
+            size = match kind {
+                RecordKind::Struct => align_to(size, member_align) + member_size,
+                RecordKind::Union => size.max(member_size),
+            };
+        }
+        let size = align_to(size, align);
+        let fields: Vec<_> = match kind {
+            RecordKind::Struct => resolved
+                .iter()
+                .map(|member| self.llvm_type(&member.ty).unwrap())
+                .collect(),
+            // the most aligned member, padded to the size of the union
+            RecordKind::Union => match resolved
+                .iter()
+                .max_by_key(|member| self.size_align(&member.ty).1)
+            {
+                None => Vec::new(),
+                Some(member) => {
+                    let padding = size - self.size_align(&member.ty).0;
+                    let mut fields = vec![self.llvm_type(&member.ty).unwrap()];
+                    if padding > 0 {
+                        let bytes = self.context.i8_type().array_type(padding as u32);
+                        fields.push(bytes.as_basic_type_enum());
+                    }
+                    fields
+                }
+            },
+        };
+        let record = &mut self.records[id];
+        record.llvm_type.set_body(&fields, false);
+        record.members = Some(resolved);
+        record.size = size;
+        record.align = align;
+        Ok(())
+    }
+
+    /// Names of the members accessible in a record, including those of its
+    /// anonymous members.
+    fn member_names(&self, members: &[RecordMember]) -> Vec<(String, Span)> {
+        let mut names = Vec::new();
+        for member in members {
+            match (&member.name, &member.ty) {
+                (Some(name), _) => names.push((name.to_owned(), member.span)),
+                (None, Type::Record(record)) => {
+                    let members = self.records[record.id].members.as_deref();
+                    names.extend(self.member_names(members.unwrap_or_default()));
+                }
+                (None, _) => {}
+            }
+        }
+        names
+    }
+
+    pub(super) fn record_llvm_type(&self, record: &RecordType) -> StructType<'ctx> {
+        self.records[record.id].llvm_type
+    }
+
+    /// Size and alignment of a defined record, in bytes.
+    pub(super) fn record_layout(&self, record: &RecordType) -> (u64, u64) {
+        let record = &self.records[record.id];
+        (record.size, record.align)
+    }
+
+    /// Where a record was defined, or first declared.
+    pub(super) fn record_span(&self, id: usize) -> Span {
+        self.records[id].span
+    }
+
+    pub(super) fn is_defined(&self, record: &RecordType) -> bool {
+        self.records[record.id].members.is_some()
+    }
+
+    /// Positions of the members leading to the member `name` of a record,
+    /// through anonymous members. `None` if there is no such member.
+    pub(super) fn find_member(&self, record: &RecordType, name: &str) -> Option<Vec<usize>> {
+        let members = self.records[record.id].members.as_ref()?;
+        for (position, member) in members.iter().enumerate() {
+            match (&member.name, &member.ty) {
+                (Some(member_name), _) if member_name == name => return Some(vec![position]),
+                (None, Type::Record(inner)) => {
+                    if let Some(mut path) = self.find_member(inner, name) {
+                        path.insert(0, position);
+                        return Some(path);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Type of the member at `position` in a defined record, if any.
+    pub(super) fn member_type(&self, record: &RecordType, position: usize) -> Option<Type> {
+        let members = self.records[record.id].members.as_ref()?;
+        members.get(position).map(|member| member.ty.clone())
+    }
+
+    /// Address and type of the member at `position` of the record at
+    /// `pointer`. Union members all start at the address of the union.
+    pub(super) fn member_pointer(
+        &self,
+        pointer: PointerValue<'ctx>,
+        record: &RecordType,
+        position: usize,
+    ) -> anyhow::Result<(PointerValue<'ctx>, Type)> {
+        let Record {
+            kind,
+            llvm_type,
+            members,
+            ..
+        } = &self.records[record.id];
+        let member = &members.as_ref().unwrap()[position];
+        let pointer = match kind {
+            RecordKind::Struct => {
+                let name = member.name.as_deref().unwrap_or("anon");
+                self.builder
+                    .build_struct_gep(*llvm_type, pointer, position as u32, name)?
+            }
+            RecordKind::Union => pointer,
+        };
+        Ok((pointer, member.ty.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{has_lines, lower, SemaError};
+
+    #[test]
+    fn member_access_through_a_pointer() {
+        let ir = lower("struct s { char c; int x; }; int f(struct s *p) { return p->x; }").unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "%struct.s = type { i8, i32 }",
+                    "define i32 @f(ptr %0)",
+                    "%x = getelementptr inbounds %struct.s, ptr %load, i32 0, i32 1",
+                    "%load1 = load i32, ptr %x",
+                ]
+            ),
+            "{ir}"
+        );
+    }
+
+    #[test]
+    fn member_access_through_incomplete_tags() {
+        let prelude = "struct S; struct S *p; struct S get(void);\n";
+        for expr in ["p->x", "(*p).x"] {
+            let code = format!("{prelude}int f(void) {{ return {expr}; }}");
+            let err = lower(&code).unwrap_err();
+            let SemaError::IncompleteRecord { ty, span } = &err else {
+                panic!("{expr}: {err:?}");
+            };
+            assert_eq!(ty.to_string(), "struct S");
+            assert_eq!(&code[span.start..span.end], "x", "{expr}");
+        }
+        let code = format!("{prelude}int f(void) {{ return get().x; }}");
+        let err = lower(&code).unwrap_err();
+        let SemaError::IncompleteType { name, span } = &err else {
+            panic!("{err:?}");
+        };
+        assert_eq!(name, "struct S");
+        assert_eq!(&code[span.start..span.end], "get()");
+    }
+}
@@ -1,43 +1,33 @@
-use inkwell::values::{BasicValue, BasicValueEnum};
+use inkwell::{basic_block::BasicBlock, values::IntValue};
 
-use crate::syntax_analysis::statement::{JumpStatement, Statement};
+use crate::{
+    diagnostics::{Diagnostic, Span},
+    syntax_analysis::{
+        expression::Expression,
+        statement::{ForInit, JumpStatement, Statement},
+        types::Type,
+    },
+};
 
-use super::IR;
+use super::{constant::IntegerConstant, JumpTargets, Label, SemaError, SwitchLabels, IR};
 
 impl<'ctx> IR<'ctx> {
     pub(super) fn analysis_statement(&mut self, stmt: Statement) -> anyhow::Result<()> {
         match stmt {
             Statement::Block(block) => {
-                self.analysis_block(block)?;
+                self.in_scope(|ir| ir.analysis_block(block))?;
             }
             Statement::Empty => (),
+            Statement::Error(span) => {
+                anyhow::bail!("Syntax error node at {span:?} reached semantic analysis")
+            }
             Statement::Expression(expr) => {
                 self.analysis_expression(expr)?;
             }
             Statement::If(condition, true_stmt, false_stmt) => {
                 tracing::trace!("If {condition:?}");
-                let Some(condition) = self.analysis_expression(condition)? else {
-                    anyhow::bail!("Condition cannot be void");
-                };
-                let condition = match condition {
-                    BasicValueEnum::IntValue(condition) => {
-                        use inkwell::IntPredicate;
-                        let condition = self.builder.build_int_cast(
-                            condition,
-                            self.context.bool_type(),
-                            "to_bool",
-                        )?;
-                        self.builder.build_int_compare(
-                            IntPredicate::NE,
-                            condition,
-                            self.context.bool_type().const_zero(),
-                            "cond",
-                        )?
-                    }
-                    _ => anyhow::bail!("Unimplemented condition type"),
-                };
+                let condition = self.analysis_condition(condition)?;
                 let this_block = self.builder.get_insert_block().unwrap();
-                let _this_func = this_block.get_parent().unwrap();
                 let end_block = self.context.insert_basic_block_after(this_block, "if_end");
                 let then_block = self.context.insert_basic_block_after(this_block, "if_then");
                 if let Some(false_stmt) = false_stmt {
@@ -59,7 +49,6 @@ impl<'ctx> IR<'ctx> {
             Statement::While(condition, body) => {
                 tracing::trace!("While {condition:?}");
                 let this_block = self.builder.get_insert_block().unwrap();
-                let _this_func = this_block.get_parent().unwrap();
                 let body_block = self
                     .context
                     .insert_basic_block_after(this_block, "while_body");
@@ -71,39 +60,136 @@ impl<'ctx> IR<'ctx> {
                     .insert_basic_block_after(this_block, "while_cond");
                 self.builder.build_unconditional_branch(cond_block)?;
                 self.builder.position_at_end(cond_block);
-                let Some(condition) = self.analysis_expression(condition)? else {
-                    anyhow::bail!("Condition cannot be void");
-                };
-                let condition = match condition {
-                    BasicValueEnum::IntValue(condition) => {
-                        use inkwell::IntPredicate;
-                        let condition = self.builder.build_int_cast(
-                            condition,
-                            self.context.bool_type(),
-                            "to_bool",
-                        )?;
-                        self.builder.build_int_compare(
-                            IntPredicate::NE,
-                            condition,
-                            self.context.bool_type().const_zero(),
-                            "cond",
-                        )?
-                    }
-                    _ => anyhow::bail!("Condition of unimplemented type"),
-                };
+                let condition = self.analysis_condition(condition)?;
                 self.builder
                     .build_conditional_branch(condition, body_block, end_block)?;
                 self.builder.position_at_end(body_block);
-                self.analysis_statement(*body)?;
+                self.analysis_loop_body(*body, end_block, cond_block)?;
                 self.builder.build_unconditional_branch(cond_block)?;
                 self.builder.position_at_end(end_block);
             }
+            Statement::DoWhile(body, condition) => {
+                tracing::trace!("Do while {condition:?}");
+                let this_block = self.builder.get_insert_block().unwrap();
+                let end_block = self.context.insert_basic_block_after(this_block, "do_end");
+                let cond_block = self.context.insert_basic_block_after(this_block, "do_cond");
+                let body_block = self.context.insert_basic_block_after(this_block, "do_body");
+                self.builder.build_unconditional_branch(body_block)?;
+                self.builder.position_at_end(body_block);
+                self.analysis_loop_body(*body, end_block, cond_block)?;
+                self.builder.build_unconditional_branch(cond_block)?;
+                self.builder.position_at_end(cond_block);
+                let condition = self.analysis_condition(condition)?;
+                self.builder
+                    .build_conditional_branch(condition, body_block, end_block)?;
+                self.builder.position_at_end(end_block);
+            }
+            Statement::For(init, condition, step, body) => {
+                tracing::trace!("For {condition:?}");
+                self.in_scope(|ir| ir.analysis_for(init, condition, step, *body))?;
+            }
+            Statement::Switch(value, body, span) => {
+                tracing::trace!("Switch {value:?}");
+                self.analysis_switch(value, *body, span)?;
+            }
+            Statement::Case(value, stmt, span) => {
+                let value_span = value.span();
+                let value = self.constant_int(&value)?;
+                let Some(labels) = self.switches.last() else {
+                    anyhow::bail!(SemaError::LabelOutsideSwitch {
+                        label: "case",
+                        span
+                    });
+                };
+                // compared as converted to the type of the switch value, so
+                // `case 300:` and `case 44:` are the same for a `char`
+                let converted = IntegerConstant::new(value.value, labels.ty);
+                if let Some((_, _, previous)) = labels
+                    .cases
+                    .iter()
+                    .find(|case| case.0.value == converted.value)
+                {
+                    anyhow::bail!(SemaError::DuplicateCase {
+                        value: converted.value,
+                        span,
+                        previous: *previous,
+                    });
+                }
+                if converted.value != value.value {
+                    let ty = Type::Integer(labels.ty);
+                    self.diagnostics.push(
+                        Diagnostic::warning(format!(
+                            "case value `{}` changes when converted to `{ty}`",
+                            value.value
+                        ))
+                        .with_primary(value_span, format!("converted to {}", converted.value)),
+                    );
+                }
+                let value = converted;
+                let case_block = self.fall_through("switch_case")?;
+                let labels = self.switches.last_mut().unwrap();
+                labels.cases.push((value, case_block, span));
+                self.analysis_statement(*stmt)?;
+            }
+            Statement::Default(stmt, span) => {
+                let Some(labels) = self.switches.last() else {
+                    anyhow::bail!(SemaError::LabelOutsideSwitch {
+                        label: "default",
+                        span,
+                    });
+                };
+                if let Some((_, previous)) = labels.default {
+                    anyhow::bail!(SemaError::MultipleDefault { span, previous });
+                }
+                let default_block = self.fall_through("switch_default")?;
+                self.switches.last_mut().unwrap().default = Some((default_block, span));
+                self.analysis_statement(*stmt)?;
+            }
+            Statement::Labeled(name, stmt, span) => {
+                let label = self.label(&name);
+                if let Some(previous) = label.definition {
+                    anyhow::bail!(SemaError::DuplicateLabel {
+                        name,
+                        span,
+                        previous,
+                    });
+                }
+                label.definition = Some(span);
+                let block = label.block;
+                let this_block = self.builder.get_insert_block().unwrap();
+                self.builder.build_unconditional_branch(block)?;
+                block.move_after(this_block).unwrap();
+                self.builder.position_at_end(block);
+                self.analysis_statement(*stmt)?;
+            }
             Statement::Jump(jump) => match jump {
-                JumpStatement::Return(ret_value) => {
+                JumpStatement::Goto(name, span) => {
+                    let label = self.label(&name);
+                    label.first_use.get_or_insert(span);
+                    let block = label.block;
+                    self.builder.build_unconditional_branch(block)?;
+                }
+                JumpStatement::Break(span) => {
+                    let Some(targets) = self.jump_targets.last() else {
+                        anyhow::bail!(SemaError::BreakOutsideLoop { span });
+                    };
+                    self.builder
+                        .build_unconditional_branch(targets.break_block)?;
+                }
+                JumpStatement::Continue(span) => {
+                    let continue_block = self
+                        .jump_targets
+                        .iter()
+                        .rev()
+                        .find_map(|targets| targets.continue_block);
+                    let Some(continue_block) = continue_block else {
+                        anyhow::bail!(SemaError::ContinueOutsideLoop { span });
+                    };
+                    self.builder.build_unconditional_branch(continue_block)?;
+                }
+                JumpStatement::Return(ret_value, span) => {
                     let ret_value = match ret_value {
-                        Some(ret_value) => self
-                            .analysis_expression(ret_value)?
-                            .map(|value| value.as_basic_value_enum()),
+                        Some(ret_value) => self.analysis_converted(ret_value)?,
                         None => None,
                     };
                     let this_func = self
@@ -112,21 +198,20 @@ impl<'ctx> IR<'ctx> {
                         .unwrap()
                         .get_parent()
                         .unwrap();
-                    let return_type = this_func.get_type().get_return_type();
+                    let return_type = self.return_type.clone();
                     match (ret_value, return_type) {
-                        (Some(ret_value), Some(return_type)) => {
-                            anyhow::ensure!(
-                                ret_value.get_type() == return_type,
-                                "Unexpected return type"
-                            );
-                            self.builder
-                                .build_store(self.return_value.clone().unwrap(), ret_value)?;
+                        (None, Type::Void) => {}
+                        (Some(_), Type::Void) => {
+                            anyhow::bail!(SemaError::UnexpectedReturnValue { span })
                         }
-                        (None, None) => {}
-                        (None, Some(_)) => {
-                            anyhow::bail!("This function must return non-void value.")
+                        (None, _) => anyhow::bail!(SemaError::MissingReturnValue { span }),
+                        (Some(ret_value), return_type) => {
+                            let Some(ret_value) = self.convert(ret_value, &return_type)? else {
+                                anyhow::bail!(SemaError::ReturnTypeMismatch { span });
+                            };
+                            self.builder
+                                .build_store(self.return_value.unwrap(), ret_value)?;
                         }
-                        (Some(_), None) => anyhow::bail!("This function must return nothing."),
                     }
                     let return_block = this_func.get_last_basic_block().unwrap();
                     self.builder.build_unconditional_branch(return_block)?;
@@ -135,4 +220,251 @@ impl<'ctx> IR<'ctx> {
         }
         Ok(())
     }
+
+    /// Lower a `for` statement, in the scope of its declaration if any.
+    fn analysis_for(
+        &mut self,
+        init: Option<ForInit>,
+        condition: Option<Expression>,
+        step: Option<Expression>,
+        body: Statement,
+    ) -> anyhow::Result<()> {
+        match init {
+            Some(ForInit::Declaration(decls)) => {
+                for decl in decls {
+                    self.analysis_declaration(decl)?;
+                }
+            }
+            Some(ForInit::Expression(init)) => {
+                self.analysis_expression(init)?;
+            }
+            None => {}
+        }
+        let this_block = self.builder.get_insert_block().unwrap();
+        let end_block = self.context.insert_basic_block_after(this_block, "for_end");
+        let step_block = self
+            .context
+            .insert_basic_block_after(this_block, "for_step");
+        let body_block = self
+            .context
+            .insert_basic_block_after(this_block, "for_body");
+        let cond_block = self
+            .context
+            .insert_basic_block_after(this_block, "for_cond");
+        self.builder.build_unconditional_branch(cond_block)?;
+        self.builder.position_at_end(cond_block);
+        match condition {
+            Some(condition) => {
+                let condition = self.analysis_condition(condition)?;
+                self.builder
+                    .build_conditional_branch(condition, body_block, end_block)?;
+            }
+            // a missing condition is always true
+            None => {
+                self.builder.build_unconditional_branch(body_block)?;
+            }
+        }
+        self.builder.position_at_end(body_block);
+        self.analysis_loop_body(body, end_block, step_block)?;
+        self.builder.build_unconditional_branch(step_block)?;
+        self.builder.position_at_end(step_block);
+        if let Some(step) = step {
+            self.analysis_expression(step)?;
+        }
+        self.builder.build_unconditional_branch(cond_block)?;
+        self.builder.position_at_end(end_block);
+        Ok(())
+    }
+
+    /// Lower a switch: the body is lowered first to collect its labels, then
+    /// the `switch` instruction jumping to them.
+    fn analysis_switch(
+        &mut self,
+        value: Expression,
+        body: Statement,
+        span: Span,
+    ) -> anyhow::Result<()> {
+        let value_span = value.span();
+        let Some(value) = self.analysis_expression(value)? else {
+            anyhow::bail!(SemaError::VoidValue {
+                what: "switch value",
+                span: value_span,
+                operator: Some(span),
+            });
+        };
+        let value = self.promote(value)?;
+        let Type::Integer(ty) = value.ty else {
+            anyhow::bail!(SemaError::NonIntegerSwitch {
+                ty: value.ty,
+                span: value_span,
+            });
+        };
+        let value = value.value.into_int_value();
+        let switch_block = self.builder.get_insert_block().unwrap();
+        let end_block = self
+            .context
+            .insert_basic_block_after(switch_block, "switch_end");
+        // statements before the first label are unreachable
+        let body_block = self
+            .context
+            .insert_basic_block_after(switch_block, "switch_body");
+        self.builder.position_at_end(body_block);
+        self.jump_targets.push(JumpTargets {
+            break_block: end_block,
+            continue_block: None,
+        });
+        self.switches.push(SwitchLabels {
+            ty,
+            cases: Vec::new(),
+            default: None,
+        });
+        let result = self.analysis_statement(body);
+        self.jump_targets.pop();
+        let labels = self.switches.pop().unwrap();
+        result?;
+        self.builder.build_unconditional_branch(end_block)?;
+        self.builder.position_at_end(switch_block);
+        let cases: Vec<_> = labels
+            .cases
+            .into_iter()
+            .map(|(case, block, _)| {
+                let case = value.get_type().const_int(case.value as u64, false);
+                (case, block)
+            })
+            .collect();
+        let default_block = labels.default.map_or(end_block, |(block, _)| block);
+        self.builder.build_switch(value, default_block, &cases)?;
+        self.builder.position_at_end(end_block);
+        Ok(())
+    }
+
+    /// Start a block named `name` for a label, which the previous statement
+    /// falls through to.
+    fn fall_through(&mut self, name: &str) -> anyhow::Result<BasicBlock<'ctx>> {
+        let this_block = self.builder.get_insert_block().unwrap();
+        let block = self.context.insert_basic_block_after(this_block, name);
+        self.builder.build_unconditional_branch(block)?;
+        self.builder.position_at_end(block);
+        Ok(block)
+    }
+
+    /// The label `name` of the current function, which may be used before it
+    /// is defined.
+    fn label(&mut self, name: &str) -> &mut Label<'ctx> {
+        let this_block = self.builder.get_insert_block().unwrap();
+        self.labels.entry(name.to_owned()).or_insert_with(|| Label {
+            block: self.context.insert_basic_block_after(this_block, name),
+            definition: None,
+            first_use: None,
+        })
+    }
+
+    /// Lower the body of a loop, in which `break` jumps to `break_block` and
+    /// `continue` to `continue_block`.
+    fn analysis_loop_body(
+        &mut self,
+        body: Statement,
+        break_block: BasicBlock<'ctx>,
+        continue_block: BasicBlock<'ctx>,
+    ) -> anyhow::Result<()> {
+        self.jump_targets.push(JumpTargets {
+            break_block,
+            continue_block: Some(continue_block),
+        });
+        let result = self.analysis_statement(body);
+        self.jump_targets.pop();
+        result
+    }
+
+    /// Lower the controlling expression of a selection or loop statement.
+    fn analysis_condition(&mut self, condition: Expression) -> anyhow::Result<IntValue<'ctx>> {
+        let condition_span = condition.span();
+        let Some(condition) = self.analysis_expression(condition)? else {
+            anyhow::bail!(SemaError::VoidValue {
+                what: "condition",
+                span: condition_span,
+                operator: None,
+            });
+        };
+        self.scalar_truth_value(condition, condition_span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{has_lines, lower, SemaError};
+
+    #[test]
+    fn switch_on_non_integers() {
+        for (code, value, ty) in [
+            (
+                "int f(int *p) { switch (p) { default: return 0; } }",
+                "p",
+                "int *",
+            ),
+            (
+                "struct s { int x; };\n\
+                 int f(struct s v) { switch ((v)) { case 1: return 1; } return 0; }",
+                "(v)",
+                "struct s",
+            ),
+        ] {
+            let err = lower(code).unwrap_err();
+            let SemaError::NonIntegerSwitch { ty: found, span } = &err else {
+                panic!("{code}: {err:?}");
+            };
+            assert_eq!(found.to_string(), ty);
+            assert_eq!(&code[span.start..span.end], value);
+            assert_eq!(
+                err.to_string(),
+                format!("switch quantity of type `{ty}` is not an integer")
+            );
+        }
+    }
+
+    #[test]
+    fn switch_is_one_instruction() {
+        let ir = lower(
+            "int f(int x) {\n\
+                 switch (x) { case 1: return 10; case 2: case 3: return 20; default: return 0; }\n\
+             }",
+        )
+        .unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "switch i32 %load, label %switch_default [",
+                    "i32 1, label %switch_case",
+                    "i32 2, label %switch_case1",
+                    "i32 3, label %switch_case2",
+                    "]",
+                    // `case 2` falls through to `case 3`
+                    "switch_case1:",
+                    "br label %switch_case2",
+                ]
+            ),
+            "{ir}"
+        );
+    }
+
+    #[test]
+    fn goto_branches_to_its_label() {
+        let ir =
+            lower("int f(int n) { int i = 0; again: i = i + 1; if (i < n) goto again; return i; }")
+                .unwrap();
+        assert!(
+            has_lines(
+                &ir,
+                &[
+                    "br label %again",
+                    "again:",
+                    "br i1 %to_bool, label %if_then, label %if_end",
+                    "if_then:",
+                    "br label %again",
+                ]
+            ),
+            "{ir}"
+        );
+    }
 }
@@ -0,0 +1,468 @@
+use inkwell::{
+    types::{BasicType, BasicTypeEnum, FunctionType},
+    values::{BasicValue, BasicValueEnum, IntValue},
+    AddressSpace, FloatPredicate, IntPredicate,
+};
+
+use crate::{
+    diagnostics::Span,
+    syntax_analysis::types::{ArrayLength, IntegerKind, IntegerType, Type},
+};
+
+use super::{SemaError, Signature, IR};
+
+/// An rvalue with its C type.
+#[derive(Debug, Clone)]
+pub(super) struct Value<'ctx> {
+    pub value: BasicValueEnum<'ctx>,
+    pub ty: Type,
+    /// Whether the value is that of a null pointer constant, which converts
+    /// to any pointer type. Only set where such a conversion can happen.
+    pub null_pointer: bool,
+}
+
+impl<'ctx> Value<'ctx> {
+    pub fn int(value: IntValue<'ctx>, ty: IntegerType) -> Self {
+        Value {
+            value: value.as_basic_value_enum(),
+            ty: Type::Integer(ty),
+            null_pointer: false,
+        }
+    }
+}
+
+/// Type of the difference of two pointers.
+pub(super) const PTRDIFF_T: IntegerType = IntegerType {
+    kind: IntegerKind::Long,
+    signed: true,
+};
+
+/// Type of the result of `sizeof`.
+pub(super) const SIZE_T: IntegerType = IntegerType {
+    kind: IntegerKind::Long,
+    signed: false,
+};
+
+/// Type of an integer after integer promotion.
+pub(super) fn promoted(ty: IntegerType) -> IntegerType {
+    if ty.kind < IntegerKind::Int {
+        IntegerType::INT
+    } else {
+        ty
+    }
+}
+
+/// Common type of two integer operands after the usual arithmetic
+/// conversions.
+pub(super) fn common_type(lhs: IntegerType, rhs: IntegerType) -> IntegerType {
+    let (lhs, rhs) = (promoted(lhs), promoted(rhs));
+    if lhs.signed == rhs.signed {
+        return if lhs.kind >= rhs.kind { lhs } else { rhs };
+    }
+    let (signed, unsigned) = if lhs.signed { (lhs, rhs) } else { (rhs, lhs) };
+    if unsigned.kind >= signed.kind {
+        unsigned
+    } else if signed.bits() > unsigned.bits() {
+        // the signed type can represent every value of the unsigned one
+        signed
+    } else {
+        IntegerType {
+            signed: false,
+            ..signed
+        }
+    }
+}
+
+/// Type both operands of a pointer comparison or conditional are converted
+/// to: the pointer type if they agree, `void *` if either points to `void`,
+/// or the pointer type if the other operand is a null pointer constant.
+pub(super) fn composite_pointer_type(lhs: &Value, rhs: &Value) -> Option<Type> {
+    match (&lhs.ty, &rhs.ty) {
+        (Type::Pointer(lhs_pointee), Type::Pointer(rhs_pointee)) => {
+            if lhs_pointee == rhs_pointee || **lhs_pointee == Type::Void {
+                Some(lhs.ty.clone())
+            } else if **rhs_pointee == Type::Void {
+                Some(rhs.ty.clone())
+            } else {
+                None
+            }
+        }
+        (Type::Pointer(_), _) if rhs.null_pointer => Some(lhs.ty.clone()),
+        (_, Type::Pointer(_)) if lhs.null_pointer => Some(rhs.ty.clone()),
+        _ => None,
+    }
+}
+
+/// Whether a value of type `from` implicitly converts to `to`, as on
+/// assignment. `null` tells whether the value is a null pointer constant.
+pub(super) fn converts(from: &Type, to: &Type, null: bool) -> bool {
+    match (from, to) {
+        (Type::Integer(_), Type::Integer(_)) => true,
+        (Type::Pointer(from_pointee), Type::Pointer(to_pointee)) => {
+            from_pointee == to_pointee || **from_pointee == Type::Void || **to_pointee == Type::Void
+        }
+        (Type::Integer(_), Type::Pointer(_)) => null,
+        (Type::Record(from), Type::Record(to)) => from == to,
+        (Type::Pointer(_), Type::Integer(to)) => to.kind == IntegerKind::Bool,
+        _ => false,
+    }
+}
+
+impl<'ctx> IR<'ctx> {
+    /// LLVM type of the values of a C type, or `None` for `void` and
+    /// functions, which are not objects.
+    pub(super) fn llvm_type(&self, ty: &Type) -> Option<BasicTypeEnum<'ctx>> {
+        match ty {
+            Type::Void | Type::Function(..) => None,
+            Type::Integer(ty) => Some(
+                self.context
+                    .custom_width_int_type(ty.bits())
+                    .as_basic_type_enum(),
+            ),
+            // pointers are opaque in LLVM; only `Type` tracks what they point to
+            Type::Pointer(_) => Some(
+                self.context
+                    .i8_type()
+                    .ptr_type(AddressSpace::default())
+                    .as_basic_type_enum(),
+            ),
+            Type::Array(element, length) => {
+                let length = match length {
+                    // only declared; the definition elsewhere has the length
+                    ArrayLength::Unknown => 0,
+                    ArrayLength::Known(length) => *length as u32,
+                    ArrayLength::Expression(_) => {
+                        unreachable!("array length is resolved before lowering")
+                    }
+                };
+                Some(
+                    self.llvm_type(element)?
+                        .array_type(length)
+                        .as_basic_type_enum(),
+                )
+            }
+            Type::Record(record) => Some(self.record_llvm_type(record).as_basic_type_enum()),
+            Type::RecordSpecifier(_) | Type::EnumSpecifier(_) | Type::Typedef(_) => {
+                unreachable!("`{ty}` is resolved before lowering")
+            }
+        }
+    }
+
+    /// LLVM type of a function with the given signature.
+    pub(super) fn fn_type(&self, signature: &Signature) -> FunctionType<'ctx> {
+        let param_types: Box<[_]> = signature
+            .params
+            .iter()
+            .map(|param_type| self.llvm_type(param_type).unwrap().into())
+            .collect();
+        match self.llvm_type(&signature.return_type) {
+            Some(ret_type) => ret_type.fn_type(&param_types, false),
+            None => self.context.void_type().fn_type(&param_types, false),
+        }
+    }
+
+    /// Size and alignment of an object type in bytes, for an LP64 target.
+    pub(super) fn size_align(&self, ty: &Type) -> (u64, u64) {
+        match ty {
+            Type::Integer(ty) => {
+                let bytes = ty.bits() as u64 / 8;
+                (bytes, bytes)
+            }
+            Type::Pointer(_) => (8, 8),
+            Type::Array(element, length) => {
+                let (size, align) = self.size_align(element);
+                match length {
+                    ArrayLength::Known(length) => (size * length, align),
+                    _ => (0, align),
+                }
+            }
+            Type::Record(record) => self.record_layout(record),
+            Type::Void
+            | Type::Function(..)
+            | Type::RecordSpecifier(_)
+            | Type::EnumSpecifier(_)
+            | Type::Typedef(_) => {
+                unreachable!("`{ty}` has no size")
+            }
+        }
+    }
+
+    /// Whether objects of type `ty` have a known size.
+    pub(super) fn is_complete(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Void | Type::Function(..) | Type::Array(_, ArrayLength::Unknown) => false,
+            Type::Array(element, _) => self.is_complete(element),
+            Type::Record(record) => self.is_defined(record),
+            _ => true,
+        }
+    }
+
+    /// Size in bytes of the type `ty` that `sizeof` at `span` is applied to.
+    pub(super) fn size_of(&self, ty: Type, span: Span) -> anyhow::Result<u64> {
+        if !self.is_complete(&ty) {
+            anyhow::bail!(SemaError::InvalidSizeof { ty, span });
+        }
+        Ok(self.size_align(&ty).0)
+    }
+
+    /// Evaluate the array lengths in a declared type, resolve its structures
+    /// and unions, and check that array elements are complete. Only the
+    /// outermost array may have an unknown length, which an initializer can
+    /// complete.
+    pub(super) fn resolve_type(&mut self, ty: Type, span: Span) -> anyhow::Result<Type> {
+        Ok(match ty {
+            Type::Pointer(pointee) => Type::Pointer(Box::new(self.resolve_type(*pointee, span)?)),
+            Type::Function(return_type, parameters) => {
+                let return_type = self.resolve_return_type(*return_type, span)?;
+                let parameters = parameters
+                    .into_iter()
+                    .map(|parameter| self.resolve_parameter(parameter, span))
+                    .collect::<anyhow::Result<_>>()?;
+                Type::Function(Box::new(return_type), parameters)
+            }
+            Type::Array(element, length) => {
+                let element = self.resolve_type(*element, span)?;
+                if !self.is_complete(&element) {
+                    anyhow::bail!(SemaError::IncompleteElementType { ty: element, span });
+                }
+                let length = match length {
+                    ArrayLength::Expression(length) => match self.constant_int(&length)?.value {
+                        length @ 1..=0xFFFF_FFFF => ArrayLength::Known(length as u64),
+                        _ => anyhow::bail!(SemaError::InvalidArrayLength {
+                            span: length.span(),
+                        }),
+                    },
+                    length => length,
+                };
+                Type::Array(Box::new(element), length)
+            }
+            ty @ (Type::RecordSpecifier(_) | Type::EnumSpecifier(_)) => {
+                self.resolve_specifier(ty)?
+            }
+            Type::Typedef(name) => match self.typedef_type(&name) {
+                Some(ty) => ty,
+                None => anyhow::bail!("Typedef name `{name}` is not in scope"),
+            },
+            ty => ty,
+        })
+    }
+
+    /// Resolve a structure, union or enumeration specifier. One that defines
+    /// a type defines it once, however many declarators share it, as in
+    /// `struct s { int x; } a, *b;`.
+    fn resolve_specifier(&mut self, ty: Type) -> anyhow::Result<Type> {
+        let (span, defines) = match &ty {
+            Type::RecordSpecifier(specifier) => (specifier.span, specifier.members.is_some()),
+            Type::EnumSpecifier(specifier) => (specifier.span, specifier.enumerators.is_some()),
+            _ => unreachable!(),
+        };
+        if let Some(defined) = self.defined_types.get(&span) {
+            return Ok(defined.clone());
+        }
+        let resolved = match ty {
+            Type::RecordSpecifier(specifier) => {
+                Type::Record(self.resolve_record(*specifier, false)?)
+            }
+            Type::EnumSpecifier(specifier) => Type::Integer(self.resolve_enum(*specifier)?),
+            _ => unreachable!(),
+        };
+        if defines {
+            self.defined_types.insert(span, resolved.clone());
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve the return type of a function, which cannot be an array or a
+    /// function.
+    pub(super) fn resolve_return_type(&mut self, ty: Type, span: Span) -> anyhow::Result<Type> {
+        let ty = self.resolve_type(ty, span)?;
+        if let Type::Array(..) | Type::Function(..) = ty {
+            anyhow::bail!(SemaError::InvalidReturnType { ty, span });
+        }
+        Ok(ty)
+    }
+
+    /// Resolve the type of a parameter. A parameter declared as an array is
+    /// a pointer to its first element, and one declared as a function a
+    /// pointer to it.
+    pub(super) fn resolve_parameter(&mut self, ty: Type, span: Span) -> anyhow::Result<Type> {
+        Ok(match self.resolve_type(ty, span)? {
+            Type::Array(element, _) => Type::Pointer(element),
+            ty @ Type::Function(..) => Type::Pointer(Box::new(ty)),
+            ty => ty,
+        })
+    }
+
+    /// Integer promotion: integers of lower rank than `int` become `int`,
+    /// which can represent all their values.
+    pub(super) fn promote(&self, value: Value<'ctx>) -> anyhow::Result<Value<'ctx>> {
+        match value.ty {
+            Type::Integer(ty) if ty != promoted(ty) => {
+                let value = self.cast_int(value.value.into_int_value(), ty, promoted(ty))?;
+                Ok(Value::int(value, promoted(ty)))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Usual arithmetic conversions: bring both operands of the binary
+    /// operator at `operator`, which must be integers, to their common type.
+    pub(super) fn usual_arithmetic_conversions(
+        &self,
+        lhs: Value<'ctx>,
+        rhs: Value<'ctx>,
+        operator: Span,
+    ) -> anyhow::Result<(IntValue<'ctx>, IntValue<'ctx>, IntegerType)> {
+        let (Type::Integer(lhs_type), Type::Integer(rhs_type)) = (&lhs.ty, &rhs.ty) else {
+            anyhow::bail!(SemaError::InvalidOperands {
+                lhs: lhs.ty,
+                rhs: rhs.ty,
+                span: operator,
+            });
+        };
+        let common = common_type(*lhs_type, *rhs_type);
+        Ok((
+            self.cast_int(lhs.value.into_int_value(), *lhs_type, common)?,
+            self.cast_int(rhs.value.into_int_value(), *rhs_type, common)?,
+            common,
+        ))
+    }
+
+    /// Convert an integer between integer types. Conversion to `_Bool`
+    /// yields 1 for any nonzero value.
+    pub(super) fn cast_int(
+        &self,
+        value: IntValue<'ctx>,
+        from: IntegerType,
+        to: IntegerType,
+    ) -> anyhow::Result<IntValue<'ctx>> {
+        let to_type = self.context.custom_width_int_type(to.bits());
+        if to.kind == IntegerKind::Bool && from.kind != IntegerKind::Bool {
+            let truth = self.builder.build_int_compare(
+                IntPredicate::NE,
+                value,
+                value.get_type().const_zero(),
+                "to_bool",
+            )?;
+            return Ok(self.builder.build_int_z_extend(truth, to_type, "to_bool")?);
+        }
+        Ok(self
+            .builder
+            .build_int_cast_sign_flag(value, to_type, from.signed, "conv")?)
+    }
+
+    /// Implicitly convert `value` to `to`, as on assignment. Pointers convert
+    /// to and from `void *` and to `_Bool`, and null pointer constants to any
+    /// pointer type. Returns `None` if there is no such conversion, as
+    /// `converts` tells.
+    pub(super) fn convert(
+        &self,
+        value: Value<'ctx>,
+        to: &Type,
+    ) -> anyhow::Result<Option<BasicValueEnum<'ctx>>> {
+        if !converts(&value.ty, to, value.null_pointer) {
+            return Ok(None);
+        }
+        let converted = match (&value.ty, to) {
+            (Type::Integer(from), Type::Integer(to)) => self
+                .cast_int(value.value.into_int_value(), *from, *to)?
+                .as_basic_value_enum(),
+            (Type::Integer(_), Type::Pointer(_)) => self
+                .llvm_type(to)
+                .unwrap()
+                .into_pointer_type()
+                .const_null()
+                .as_basic_value_enum(),
+            (Type::Pointer(_), Type::Integer(to)) => {
+                let truth = self
+                    .builder
+                    .build_is_not_null(value.value.into_pointer_value(), "to_bool")?;
+                let to_type = self.context.custom_width_int_type(to.bits());
+                self.builder
+                    .build_int_z_extend(truth, to_type, "to_bool")?
+                    .as_basic_value_enum()
+            }
+            _ => value.value,
+        };
+        Ok(Some(converted))
+    }
+
+    /// Explicitly convert `value` to the scalar type `to`, as the cast at
+    /// `span` does. Integers and pointers convert to each other, with the
+    /// bits of the integer as the address.
+    pub(super) fn cast(
+        &self,
+        value: Value<'ctx>,
+        to: &Type,
+        span: Span,
+    ) -> anyhow::Result<Value<'ctx>> {
+        let result = match (&value.ty, to) {
+            (Type::Pointer(_), Type::Pointer(_)) => value.value,
+            (Type::Integer(from), Type::Pointer(_)) => {
+                let address = IntegerType {
+                    kind: IntegerKind::Long,
+                    signed: from.signed,
+                };
+                let address = self.cast_int(value.value.into_int_value(), *from, address)?;
+                let pointer_type = self.llvm_type(to).unwrap().into_pointer_type();
+                self.builder
+                    .build_int_to_ptr(address, pointer_type, "to_pointer")?
+                    .as_basic_value_enum()
+            }
+            (Type::Pointer(_), Type::Integer(to_int)) if to_int.kind != IntegerKind::Bool => {
+                let int_type = self.context.custom_width_int_type(to_int.bits());
+                self.builder
+                    .build_ptr_to_int(value.value.into_pointer_value(), int_type, "to_int")?
+                    .as_basic_value_enum()
+            }
+            (Type::Integer(_) | Type::Pointer(_), Type::Integer(_)) => {
+                self.convert(value, to)?.unwrap()
+            }
+            _ => anyhow::bail!(SemaError::InvalidCast {
+                from: value.ty,
+                to: to.clone(),
+                span,
+            }),
+        };
+        Ok(Value {
+            value: result,
+            ty: to.clone(),
+            null_pointer: false,
+        })
+    }
+
+    /// Whether the operand at `span` is nonzero, compared at its full width.
+    /// Only scalars have a truth value.
+    pub(super) fn scalar_truth_value(
+        &self,
+        value: Value<'ctx>,
+        span: Span,
+    ) -> anyhow::Result<IntValue<'ctx>> {
+        Ok(match value.value {
+            BasicValueEnum::IntValue(value) => self.builder.build_int_compare(
+                IntPredicate::NE,
+                value,
+                value.get_type().const_zero(),
+                "to_bool",
+            )?,
+            BasicValueEnum::FloatValue(value) => self.builder.build_float_compare(
+                FloatPredicate::UNE,
+                value,
+                value.get_type().const_zero(),
+                "to_bool",
+            )?,
+            BasicValueEnum::PointerValue(value) => {
+                self.builder.build_is_not_null(value, "to_bool")?
+            }
+            _ => anyhow::bail!(SemaError::NotScalar { ty: value.ty, span }),
+        })
+    }
+
+    /// Widen an `i1` to an `int` 0 or 1.
+    pub(super) fn bool_to_int(&self, value: IntValue<'ctx>) -> anyhow::Result<Value<'ctx>> {
+        let value =
+            self.builder
+                .build_int_z_extend(value, self.context.i32_type(), "bool_to_int")?;
+        Ok(Value::int(value, IntegerType::INT))
+    }
+}
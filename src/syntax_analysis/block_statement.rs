@@ -1,6 +1,6 @@
-use crate::lexical_analysis::{Keyword, Symbol, Token};
+use crate::lexical_analysis::{Symbol, Token};
 
-use super::{declaration::Declaration, next, statement::Statement};
+use super::{declaration::Declaration, expected, next, statement::Statement, ParseContext};
 
 #[derive(Debug)]
 pub struct Block(pub Vec<BlockInner>);
@@ -12,24 +12,41 @@ pub enum BlockInner {
 }
 
 impl Block {
-    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        let (mut tokens, Token::Symbol(Symbol::LeftBrace)) = next(tokens)? else {
-            anyhow::bail!("Expected block statement. Block statement should start with \"{{\"");
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        ctx.in_scope(|ctx| Block::parse_body(tokens, ctx))
+    }
+
+    fn parse_body<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (mut remain, Token::Symbol(Symbol::LeftBrace)) = next(tokens)? else {
+            return Err(expected(ctx, tokens, "`{`"));
         };
         let mut body = Vec::new();
         loop {
-            match tokens {
+            match remain {
                 [Token::Symbol(Symbol::RightBrace), tokens @ ..] => {
                     break Ok((tokens, Block(body)));
                 }
-                [Token::Keyword(Keyword::Int | Keyword::Void), ..] => {
-                    let (remain, decl) = Declaration::parse(tokens)?;
-                    tokens = remain;
-                    body.push(BlockInner::Declaration(decl));
+                [Token::End, ..] => return Err(expected(ctx, remain, "`}`")),
+                _ if ctx.starts_declaration(remain) => {
+                    let (tokens, decls) = match Declaration::parse(remain, ctx) {
+                        Ok(result) => result,
+                        Err(err) => {
+                            let (tokens, span) = ctx.recover(err, remain)?;
+                            (tokens, vec![Declaration::Error(span)])
+                        }
+                    };
+                    remain = tokens;
+                    body.extend(decls.into_iter().map(BlockInner::Declaration));
                 }
                 _ => {
-                    let (remain, stmts) = Statement::parse(tokens)?;
-                    tokens = remain;
+                    let (tokens, stmts) = Statement::parse(remain, ctx)?;
+                    remain = tokens;
                     body.push(BlockInner::Statement(stmts));
                 }
             }
@@ -1,70 +1,140 @@
 use crate::{
+    diagnostics::Span,
     lexical_analysis::{Keyword, Symbol, Token},
-    syntax_analysis::expression::eval,
 };
 
 use super::{
-    block_statement::Block, expression::Expression, next,
-    parameter_definition::ParameterDefinition, types::Type,
+    block_statement::Block, declarator::Declarator, expected, initializer::Initializer,
+    parameter_definition::ParameterDefinition, types::Type, ParseContext,
 };
 
 #[derive(Debug)]
 pub enum Declaration {
-    Variable(Type, String, Option<Expression>),
-    Function(Type, String, Vec<ParameterDefinition>, Option<Block>),
+    Variable(Type, String, Option<Initializer>, Span),
+    Function(Type, String, Vec<ParameterDefinition>, Option<Block>, Span),
+    /// A declaration of only a structure, union or enumeration, such as
+    /// `struct list;`, with the span of its tag or keyword.
+    Tag(Type, Span),
+    /// `typedef type name;`, with the span of the name.
+    Typedef(Type, String, Span),
+    /// A declaration with syntax errors, skipped by error recovery.
+    Error(Span),
 }
 
 impl Declaration {
-    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        let (tokens, decl_type) = Type::parse(tokens)?;
-        let (tokens, Token::Identifier(id)) = next(tokens)? else {
-            anyhow::bail!("Expected identifier");
-        };
-        let (mut tokens, token_after_id) = next(tokens)?;
-        match token_after_id {
-            Token::Symbol(Symbol::LeftParen) => {
-                let mut params = Vec::new();
-                match tokens {
-                    [Token::Keyword(Keyword::Void), Token::Symbol(Symbol::RightParen), remain @ ..]
-                    | [Token::Symbol(Symbol::RightParen), remain @ ..] => tokens = remain,
-                    _ => loop {
-                        let (remain, param) = ParameterDefinition::parse(tokens)?;
-                        params.push(param);
-                        let (remain, token_after_param) = next(remain)?;
-                        tokens = remain;
-                        match token_after_param {
-                            Token::Symbol(Symbol::Comma) => continue,
-                            Token::Symbol(Symbol::RightParen) => break,
-                            _ => anyhow::bail!("Expected \",\" or \";\""),
+    /// Parse a declaration, which declares one name for each declarator in
+    /// its list, as in `int a, b = 2, *c;`. The declarators share the
+    /// declaration specifiers, and each name is in scope from the end of its
+    /// declarator.
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Vec<Self>)> {
+        if let [Token::Keyword(Keyword::Typedef), remain @ ..] = tokens {
+            return Declaration::parse_typedef(remain, ctx);
+        }
+        let (tokens, decl_type) = Type::parse(tokens, ctx)?;
+        if let [Token::Symbol(Symbol::Semicolon), remain @ ..] = tokens {
+            let span = match &decl_type {
+                Type::RecordSpecifier(specifier) => Some(specifier.span),
+                Type::EnumSpecifier(specifier) => Some(specifier.span),
+                _ => None,
+            };
+            if let Some(span) = span {
+                return Ok((remain, vec![Declaration::Tag(decl_type, span)]));
+            }
+        }
+        let mut decls = Vec::new();
+        let mut tokens = tokens;
+        loop {
+            let (remain, declarator) = Declarator::parse(tokens, ctx, decl_type.clone())?;
+            let Declarator {
+                name,
+                ty,
+                parameters,
+                span: id_span,
+            } = declarator;
+            let id = name.unwrap();
+            ctx.declare(&id, false);
+            let (remain, decl) = match (ty, parameters) {
+                (Type::Function(return_type, _), Some(params)) => {
+                    // only the sole declarator of a declaration may define a
+                    // function
+                    if decls.is_empty() && matches!(remain, [Token::Symbol(Symbol::LeftBrace), ..])
+                    {
+                        // parameters are in scope in the body
+                        let (tokens, block_stmt) = ctx.in_scope(|ctx| {
+                            for ParameterDefinition(_, param_name, _) in &params {
+                                if let Some(param_name) = param_name {
+                                    ctx.declare(param_name, false);
+                                }
+                            }
+                            Block::parse(remain, ctx)
+                        })?;
+                        let func_decl = Declaration::Function(
+                            *return_type,
+                            id,
+                            params,
+                            Some(block_stmt),
+                            id_span,
+                        );
+                        tracing::trace!("Function definition: {func_decl:?}");
+                        return Ok((tokens, vec![func_decl]));
+                    }
+                    let func_decl = Declaration::Function(*return_type, id, params, None, id_span);
+                    tracing::trace!("Function declaration: {func_decl:?}");
+                    (remain, func_decl)
+                }
+                (var_type, _) => {
+                    let (after_value, value) = match remain {
+                        [Token::Symbol(Symbol::Equal), after_equal @ ..] => {
+                            let (after_value, value) = Initializer::parse(after_equal, ctx)?;
+                            (after_value, Some(value))
                         }
-                    },
+                        _ => (remain, None),
+                    };
+                    let var_decl = Declaration::Variable(var_type, id, value, id_span);
+                    tracing::trace!("Variable declaration: {var_decl:?}");
+                    (after_value, var_decl)
                 }
-                if let [Token::Symbol(Symbol::Semicolon), tokens @ ..] = tokens {
-                    let func_decl = Declaration::Function(decl_type, id, params, None);
-                    tracing::trace!("Function definition: {func_decl:?}");
-                    Ok((tokens, func_decl))
-                } else {
-                    let (tokens, block_stmt) = Block::parse(tokens)?;
-                    let func_decl = Declaration::Function(decl_type, id, params, Some(block_stmt));
-                    tracing::trace!("Function definition: {func_decl:?}");
-                    Ok((tokens, func_decl))
+            };
+            let expectation = match &decl {
+                Declaration::Function(..) if decls.is_empty() => "`{`, `,` or `;`",
+                Declaration::Variable(_, _, Some(_), _) | Declaration::Function(..) => "`,` or `;`",
+                Declaration::Variable(Type::Array(..), _, _, _) => "`[`, `,`, `;` or `=`",
+                _ => "`(`, `[`, `,`, `;` or `=`",
+            };
+            decls.push(decl);
+            match remain {
+                [Token::Symbol(Symbol::Comma), after_comma @ ..] => tokens = after_comma,
+                [Token::Symbol(Symbol::Semicolon), after_semicolon @ ..] => {
+                    return Ok((after_semicolon, decls))
                 }
+                _ => return Err(expected(ctx, remain, expectation)),
             }
-            Token::Symbol(Symbol::Semicolon) => {
-                let var_decl = Declaration::Variable(decl_type, id, None);
-                tracing::trace!("Variable declaration: {var_decl:?}");
-                Ok((tokens, var_decl))
-            }
-            Token::Symbol(Symbol::Equal) => {
-                let (tokens, expr) = Expression::parse(tokens)?;
-                let var_decl = Declaration::Variable(decl_type, id, Some(eval(expr)));
-                tracing::trace!("Variable declaration: {var_decl:?}");
-                let (tokens, Token::Symbol(Symbol::Semicolon)) = next(tokens)? else {
-                    anyhow::bail!("Expected \";\"");
-                };
-                Ok((tokens, var_decl))
+        }
+    }
+
+    /// Parse what follows `typedef`, declaring each name in its list as a
+    /// typedef name, as in `typedef int A, *B;`.
+    fn parse_typedef<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Vec<Self>)> {
+        let (mut remain, ty) = Type::parse(tokens, ctx)?;
+        let mut decls = Vec::new();
+        loop {
+            let (after_declarator, declarator) = Declarator::parse(remain, ctx, ty.clone())?;
+            let name = declarator.name.unwrap();
+            ctx.declare(&name, true);
+            decls.push(Declaration::Typedef(declarator.ty, name, declarator.span));
+            match after_declarator {
+                [Token::Symbol(Symbol::Comma), after_comma @ ..] => remain = after_comma,
+                [Token::Symbol(Symbol::Semicolon), after_semicolon @ ..] => {
+                    return Ok((after_semicolon, decls))
+                }
+                _ => return Err(expected(ctx, after_declarator, "`,` or `;`")),
             }
-            _ => anyhow::bail!("Expected function or variable"),
         }
     }
 }
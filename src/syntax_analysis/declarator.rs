@@ -0,0 +1,329 @@
+use crate::{
+    diagnostics::Span,
+    lexical_analysis::{Symbol, Token},
+};
+
+use super::{
+    expected,
+    expression::Expression,
+    next,
+    parameter_definition::ParameterDefinition,
+    types::{ArrayLength, Type},
+    ParseContext,
+};
+
+/// A type derived by declarator suffixes, and the parameters of the first
+/// suffix if it is a parameter list.
+type Suffixed = (Type, Option<Vec<ParameterDefinition>>);
+
+/// What a declarator declares: a name, nothing as in a cast, or either as in
+/// a parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeclaratorKind {
+    Named,
+    Abstract,
+    Parameter,
+}
+
+impl DeclaratorKind {
+    /// Whether the `(` before `inner` encloses a nested declarator rather
+    /// than starting a parameter list. In a parameter, `(T)` with a typedef
+    /// name `T` is a parameter list.
+    fn is_nested(self, ctx: &ParseContext, inner: &[Token]) -> bool {
+        match inner {
+            _ if self == DeclaratorKind::Named => true,
+            [Token::Symbol(Symbol::Star | Symbol::LeftParen | Symbol::LeftBracket), ..] => true,
+            [Token::Identifier(name), ..] => {
+                self == DeclaratorKind::Parameter && !ctx.is_typedef_name(name)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The name declared by a declarator, if any, and the type it derives from
+/// the declaration specifiers. In `int *a[3]`, `a` is an array of 3 pointers
+/// to `int`.
+#[derive(Debug)]
+pub struct Declarator {
+    pub name: Option<String>,
+    pub ty: Type,
+    /// The parameters, if it declares a function as in `f(int x)`, whose
+    /// names are in scope in the body of the function.
+    pub parameters: Option<Vec<ParameterDefinition>>,
+    /// Span of the name, or of the first token if there is none.
+    pub span: Span,
+}
+
+impl Declarator {
+    /// Parse a declarator of a name, deriving its type from `base`.
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        base: Type,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        Declarator::parse_kind(tokens, ctx, base, DeclaratorKind::Named)
+    }
+
+    /// Parse an abstract declarator, which declares no name, as in
+    /// `(int (*)[3])p`.
+    pub fn parse_abstract<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        base: Type,
+    ) -> anyhow::Result<(&'a [Token], Type)> {
+        let (remain, declarator) =
+            Declarator::parse_kind(tokens, ctx, base, DeclaratorKind::Abstract)?;
+        Ok((remain, declarator.ty))
+    }
+
+    /// Parse the declarator of a parameter, which may declare a name.
+    pub fn parse_parameter<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        base: Type,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        Declarator::parse_kind(tokens, ctx, base, DeclaratorKind::Parameter)
+    }
+
+    fn parse_kind<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        base: Type,
+        kind: DeclaratorKind,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let mut remain = tokens;
+        let mut ty = base;
+        while let [Token::Symbol(Symbol::Star), after_star @ ..] = remain {
+            ty = Type::Pointer(Box::new(ty));
+            remain = after_star;
+        }
+        match remain {
+            [Token::Symbol(Symbol::LeftParen), inner @ ..] if kind.is_nested(ctx, inner) => {
+                // the suffixes after the parentheses derive the type the
+                // nested declarator derives from: in `int (*fp)(void)`, `fp`
+                // is a pointer to a function, so parse the nested declarator
+                // from a placeholder and put that type in its place after
+                let (after_inner, declarator) =
+                    Declarator::parse_kind(inner, ctx, Type::Void, kind)?;
+                let [Token::Symbol(Symbol::RightParen), after_paren @ ..] = after_inner else {
+                    return Err(expected(ctx, after_inner, "`)`"));
+                };
+                let (remain, (ty, parameters)) = Declarator::parse_suffixes(after_paren, ctx, ty)?;
+                let declarator = Declarator {
+                    ty: rebase(declarator.ty, ty),
+                    ..declarator
+                };
+                // the parameters belong to the declared function only if the
+                // nested declarator derives nothing, as in `int (f)(int x)`
+                let parameters = declarator
+                    .parameters
+                    .or(parameters)
+                    .filter(|_| matches!(declarator.ty, Type::Function(..)));
+                Ok((
+                    remain,
+                    Declarator {
+                        parameters,
+                        ..declarator
+                    },
+                ))
+            }
+            [Token::Identifier(name), after_name @ ..] if kind != DeclaratorKind::Abstract => {
+                let span = ctx.span(remain);
+                let (remain, (ty, parameters)) = Declarator::parse_suffixes(after_name, ctx, ty)?;
+                let declarator = Declarator {
+                    name: Some(name.to_owned()),
+                    ty,
+                    parameters,
+                    span,
+                };
+                Ok((remain, declarator))
+            }
+            _ if kind == DeclaratorKind::Named => Err(expected(ctx, remain, "identifier")),
+            _ => {
+                let (remain, (ty, parameters)) = Declarator::parse_suffixes(remain, ctx, ty)?;
+                let declarator = Declarator {
+                    name: None,
+                    ty,
+                    parameters,
+                    span: ctx.span(tokens),
+                };
+                Ok((remain, declarator))
+            }
+        }
+    }
+
+    /// Parse the `[length]` and `(parameters)` suffixes of a declarator, each
+    /// deriving an array or function from the type the rest derive. In
+    /// `int m[3][4]`, `m` is an array of 3 arrays of 4 `int`s. Also returns
+    /// the parameters of the first suffix, if it is a parameter list.
+    fn parse_suffixes<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        base: Type,
+    ) -> anyhow::Result<(&'a [Token], Suffixed)> {
+        match tokens {
+            [Token::Symbol(Symbol::LeftBracket), remain @ ..] => {
+                let (remain, length) = match remain {
+                    [Token::Symbol(Symbol::RightBracket), remain @ ..] => {
+                        (remain, ArrayLength::Unknown)
+                    }
+                    _ => {
+                        let (remain, length) = Expression::parse_conditional(remain, ctx)?;
+                        let (remain, Token::Symbol(Symbol::RightBracket)) = next(remain)? else {
+                            return Err(expected(ctx, remain, "`]`"));
+                        };
+                        (remain, ArrayLength::Expression(Box::new(length)))
+                    }
+                };
+                let (remain, (element, _)) = Declarator::parse_suffixes(remain, ctx, base)?;
+                Ok((remain, (Type::Array(Box::new(element), length), None)))
+            }
+            [Token::Symbol(Symbol::LeftParen), remain @ ..] => {
+                let (remain, parameters) = ParameterDefinition::parse_list(remain, ctx)?;
+                let (remain, (return_type, _)) = Declarator::parse_suffixes(remain, ctx, base)?;
+                let parameter_types = parameters
+                    .iter()
+                    .map(|ParameterDefinition(ty, _, _)| ty.clone())
+                    .collect();
+                let ty = Type::Function(Box::new(return_type), parameter_types);
+                Ok((remain, (ty, Some(parameters))))
+            }
+            _ => Ok((tokens, (base, None))),
+        }
+    }
+}
+
+/// Replace the type `ty` was derived from by a declarator with `base`. In
+/// `int (*fp)(void)`, `*fp` derives a pointer to the function the suffix
+/// derives.
+fn rebase(ty: Type, base: Type) -> Type {
+    match ty {
+        Type::Pointer(pointee) => Type::Pointer(Box::new(rebase(*pointee, base))),
+        Type::Array(element, length) => Type::Array(Box::new(rebase(*element, base)), length),
+        Type::Function(return_type, parameters) => {
+            Type::Function(Box::new(rebase(*return_type, base)), parameters)
+        }
+        _ => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical_analysis::{extract_tokens, Constant};
+
+    /// Parse a declaration without the `;`, returning the declared name
+    /// and its type.
+    fn parse_declarator(code: &str) -> (Option<String>, Type) {
+        let tokens = extract_tokens(code).unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        let (remain, base) = Type::parse(&tokens.tokens, &mut ctx).unwrap();
+        let (remain, declarator) = Declarator::parse(remain, &mut ctx, base).unwrap();
+        assert_eq!(remain, [Token::End], "{code}");
+        (declarator.name, declarator.ty)
+    }
+
+    #[test]
+    fn pointer_declarators() {
+        for (code, expected) in [
+            ("int x", "int"),
+            ("char *s", "char *"),
+            ("unsigned **pp", "unsigned int **"),
+        ] {
+            let (_, ty) = parse_declarator(code);
+            assert_eq!(ty.to_string(), expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn array_lengths_from_the_outermost() {
+        let (name, parsed) = parse_declarator("int m[3][4][]");
+        assert_eq!(name.as_deref(), Some("m"));
+        let mut lengths = Vec::new();
+        let mut ty = &parsed;
+        while let Type::Array(element, length) = ty {
+            lengths.push(match length {
+                ArrayLength::Expression(length) => match **length {
+                    Expression::Constant(Constant::Int(length), _) => Some(length),
+                    _ => panic!("{length:?} is not a literal"),
+                },
+                _ => None,
+            });
+            ty = element;
+        }
+        assert_eq!(lengths, [Some(3), Some(4), None]);
+        assert_eq!(ty.to_string(), "int");
+    }
+
+    #[test]
+    fn nested_declarators_derive_inside_out() {
+        for (code, expected) in [
+            ("int *a[3]", "int *[3]"),
+            ("int (*a)[3]", "int (*)[3]"),
+            ("int (*fp)(int, char)", "int (*)(int, char)"),
+            ("char *(*table[4])(void)", "char *(*[4])(void)"),
+            ("int (*(*f)(int x))[2]", "int (*(*)(int))[2]"),
+            ("int (*a)[N * (2 + M)]", "int (*)[N * (2 + M)]"),
+            ("long m[sizeof(int)][- -1u]", "long[sizeof(int)][- -1u]"),
+            ("int ((x))", "int"),
+            (
+                "long f(int (*)(void), char [])",
+                "long (int (*)(void), char[])",
+            ),
+        ] {
+            let (name, ty) = parse_declarator(code);
+            assert!(name.is_some(), "{code}");
+            assert_eq!(ty.to_string(), expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn deeply_nested_declarators() {
+        let depth = 64;
+        let code = format!("int {}*x{}[2]", "(".repeat(depth), ")".repeat(depth));
+        let (name, ty) = parse_declarator(&code);
+        assert_eq!(name.as_deref(), Some("x"));
+        assert_eq!(ty.to_string(), "int (*)[2]");
+    }
+
+    #[test]
+    fn parameters_of_the_declared_function() {
+        for (code, names) in [
+            ("int f(int x, char *)", Some(vec![Some("x"), None])),
+            ("int (f)(int x)", Some(vec![Some("x")])),
+            ("int (*fp)(int x)", None),
+            ("int (*f(int x))(int y)", Some(vec![Some("x")])),
+            ("int f(void)", Some(vec![])),
+        ] {
+            let tokens = extract_tokens(code).unwrap();
+            let mut ctx = ParseContext::new(&tokens.spans);
+            let (remain, base) = Type::parse(&tokens.tokens, &mut ctx).unwrap();
+            let (_, declarator) = Declarator::parse(remain, &mut ctx, base).unwrap();
+            let parameters = declarator.parameters.map(|parameters| {
+                parameters
+                    .into_iter()
+                    .map(|ParameterDefinition(_, name, _)| name)
+                    .collect::<Vec<_>>()
+            });
+            let names = names.map(|names| {
+                names
+                    .into_iter()
+                    .map(|name| name.map(str::to_owned))
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(parameters, names, "{code}");
+        }
+    }
+
+    #[test]
+    fn typedef_name_in_parentheses_is_a_parameter_list() {
+        let tokens = extract_tokens("int (T)").unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        ctx.declare("T", true);
+        let (remain, base) = Type::parse(&tokens.tokens, &mut ctx).unwrap();
+        let (_, declarator) = Declarator::parse_parameter(remain, &mut ctx, base).unwrap();
+        assert_eq!(declarator.name, None);
+        assert_eq!(declarator.ty.to_string(), "int (T)");
+    }
+}
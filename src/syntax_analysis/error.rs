@@ -0,0 +1,71 @@
+use crate::diagnostics::{Diagnostic, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("expected {expected}, found {found}")]
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        span: Span,
+    },
+    #[error("parameter cannot be `void`")]
+    VoidParameter { span: Span },
+    #[error("invalid combination of type specifiers")]
+    InvalidTypeSpecifiers { span: Span },
+    /// Every syntax error in a translation unit, when the parser recovered
+    /// from more than one.
+    #[error("{} syntax errors", .0.len())]
+    Multiple(Vec<ParseError>),
+    /// A bug in the parser rather than in the parsed code.
+    #[error("internal compiler error: {message}")]
+    Internal { message: String },
+}
+
+impl ParseError {
+    /// Stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken { .. } => "E0101",
+            ParseError::VoidParameter { .. } => "E0102",
+            ParseError::InvalidTypeSpecifiers { .. } => "E0103",
+            ParseError::Multiple(_) => "E0100",
+            ParseError::Internal { .. } => "E0104",
+        }
+    }
+
+    /// Location of the error. For [`ParseError::Multiple`], the first one,
+    /// and none for [`ParseError::Internal`].
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::VoidParameter { span }
+            | ParseError::InvalidTypeSpecifiers { span } => *span,
+            ParseError::Multiple(errors) => {
+                errors.first().map(ParseError::span).unwrap_or_default()
+            }
+            ParseError::Internal { .. } => Span::default(),
+        }
+    }
+
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        let diagnostic = Diagnostic::error(self.to_string()).with_code(self.code());
+        let diagnostic = match self {
+            ParseError::UnexpectedToken { expected, span, .. } => {
+                diagnostic.with_primary(*span, format!("expected {expected}"))
+            }
+            ParseError::VoidParameter { span } => diagnostic
+                .with_primary(*span, "`void` parameter")
+                .with_help("a function without parameters is declared with `(void)`"),
+            ParseError::InvalidTypeSpecifiers { span } => {
+                diagnostic.with_primary(*span, "these specifiers do not name a type")
+            }
+            ParseError::Multiple(errors) => {
+                return errors.iter().flat_map(ParseError::to_diagnostics).collect()
+            }
+            ParseError::Internal { .. } => {
+                diagnostic.with_note("this is a bug in rcc, not in your code")
+            }
+        };
+        vec![diagnostic]
+    }
+}
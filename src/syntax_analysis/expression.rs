@@ -1,14 +1,35 @@
-use crate::lexical_analysis::{Constant, Symbol, Token};
+use crate::{
+    diagnostics::Span,
+    lexical_analysis::{Constant, Keyword, Symbol, Token},
+};
 
-use super::next;
+use super::{expected, next, types::Type, ParseContext, ParseError};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expression {
-    Paren(Box<Expression>),
-    Binary(Box<Expression>, BinaryOperator, Box<Expression>),
-    PrefixUnary(PrefixUnaryOperator, Box<Expression>),
+    Paren(Box<Expression>, Span),
+    Binary(Box<Expression>, BinaryOperator, Box<Expression>, Span),
+    PrefixUnary(PrefixUnaryOperator, Box<Expression>, Span),
+    PostfixUnary(PostfixUnaryOperator, Box<Expression>, Span),
+    /// `(type) operand`, with the span of the parenthesized type.
+    Cast(Type, Box<Expression>, Span),
+    /// `sizeof operand`, with the span of `sizeof`. The operand is not
+    /// evaluated.
+    Sizeof(Box<Expression>, Span),
+    /// `sizeof (type)`, with the span of the whole expression.
+    SizeofType(Type, Span),
+    /// `condition ? then : otherwise`, with the span of `?`.
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>, Span),
     RefOrCall(RefOrCall),
-    Constant(Constant),
+    /// `callee(args)`, with the span of the whole call.
+    Call(Box<Expression>, Vec<Expression>, Span),
+    /// `array[index]`, with the span of the whole expression.
+    Subscript(Box<Expression>, Box<Expression>, Span),
+    /// `object.member`, with the span of the member name.
+    Member(Box<Expression>, String, Span),
+    /// `pointer->member`, with the span of the member name.
+    PointerMember(Box<Expression>, String, Span),
+    Constant(Constant, Span),
     Evaluate(Box<Expression>),
 }
 
@@ -16,25 +37,42 @@ pub fn eval(expr: Expression) -> Expression {
     Expression::Evaluate(Box::new(expr))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RefOrCall {
-    FunctionCall(String, Vec<Expression>),
-    Variable(String),
+    Variable(String, Span),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryOperator {
     Plus,
     Minus,
     Multiply,
     Divide,
+    Modulo,
+    ShiftLeft,
+    ShiftRight,
     Less,
     LessEqual,
     Greater,
     GreaterEqual,
     Equal,
     NotEqual,
+    BitAnd,
+    BitXor,
+    BitOr,
+    LogicalAnd,
+    LogicalOr,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    MultiplyAssign,
+    DivideAssign,
+    ModuloAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
+    BitAndAssign,
+    BitXorAssign,
+    BitOrAssign,
     Comma,
 }
 
@@ -47,24 +85,82 @@ impl BinaryOperator {
             Token::Symbol(Symbol::Minus) => Minus,
             Token::Symbol(Symbol::Star) => Multiply,
             Token::Symbol(Symbol::Slash) => Divide,
+            Token::Symbol(Symbol::Modulo) => Modulo,
+            Token::Symbol(Symbol::LessLess) => ShiftLeft,
+            Token::Symbol(Symbol::GreaterGreater) => ShiftRight,
             Token::Symbol(Symbol::Less) => Less,
             Token::Symbol(Symbol::LessEqual) => LessEqual,
             Token::Symbol(Symbol::Greater) => Greater,
             Token::Symbol(Symbol::GreaterEqual) => GreaterEqual,
             Token::Symbol(Symbol::EqualEqual) => Equal,
             Token::Symbol(Symbol::ExclaimEqual) => NotEqual,
+            Token::Symbol(Symbol::Amp) => BitAnd,
+            Token::Symbol(Symbol::Caret) => BitXor,
+            Token::Symbol(Symbol::Pipe) => BitOr,
+            Token::Symbol(Symbol::And) => LogicalAnd,
+            Token::Symbol(Symbol::Or) => LogicalOr,
             Token::Symbol(Symbol::Equal) => Assign,
-            _ => anyhow::bail!("Expected binary operator"),
+            Token::Symbol(Symbol::PlusEqual) => PlusAssign,
+            Token::Symbol(Symbol::MinusEqual) => MinusAssign,
+            Token::Symbol(Symbol::StarEqual) => MultiplyAssign,
+            Token::Symbol(Symbol::SlashEqual) => DivideAssign,
+            Token::Symbol(Symbol::ModuloEqual) => ModuloAssign,
+            Token::Symbol(Symbol::LessLessEqual) => ShiftLeftAssign,
+            Token::Symbol(Symbol::GreaterGreaterEqual) => ShiftRightAssign,
+            Token::Symbol(Symbol::AmpEqual) => BitAndAssign,
+            Token::Symbol(Symbol::CaretEqual) => BitXorAssign,
+            Token::Symbol(Symbol::PipeEqual) => BitOrAssign,
+            Token::Symbol(Symbol::Comma) => Comma,
+            _ => anyhow::bail!(ParseError::Internal {
+                message: "Expected binary operator".to_owned(),
+            }),
         };
         Ok((tokens, op))
     }
+
+    /// Whether the left operand is assigned to, and thus not evaluated.
+    pub fn is_assignment(&self) -> bool {
+        *self == BinaryOperator::Assign || self.compound_operator().is_some()
+    }
+
+    /// Whether the operator compares its operands, yielding 0 or 1.
+    pub fn is_comparison(&self) -> bool {
+        use BinaryOperator::*;
+        matches!(
+            self,
+            Less | Greater | LessEqual | GreaterEqual | Equal | NotEqual
+        )
+    }
+
+    /// The operator applied by a compound assignment, e.g. `+` for `+=`.
+    pub fn compound_operator(&self) -> Option<BinaryOperator> {
+        use BinaryOperator::*;
+        match self {
+            PlusAssign => Some(Plus),
+            MinusAssign => Some(Minus),
+            MultiplyAssign => Some(Multiply),
+            DivideAssign => Some(Divide),
+            ModuloAssign => Some(Modulo),
+            ShiftLeftAssign => Some(ShiftLeft),
+            ShiftRightAssign => Some(ShiftRight),
+            BitAndAssign => Some(BitAnd),
+            BitXorAssign => Some(BitXor),
+            BitOrAssign => Some(BitOr),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PrefixUnaryOperator {
     Plus,
     Minus,
     Not,
+    BitNot,
+    Increment,
+    Decrement,
+    AddressOf,
+    Deref,
 }
 
 impl PrefixUnaryOperator {
@@ -75,12 +171,43 @@ impl PrefixUnaryOperator {
             Token::Symbol(Symbol::Plus) => Plus,
             Token::Symbol(Symbol::Minus) => Minus,
             Token::Symbol(Symbol::Exclaim) => Not,
-            _ => anyhow::bail!("Expected prefix unary operator"),
+            Token::Symbol(Symbol::Tilde) => BitNot,
+            Token::Symbol(Symbol::PlusPlus) => Increment,
+            Token::Symbol(Symbol::MinusMinus) => Decrement,
+            Token::Symbol(Symbol::Amp) => AddressOf,
+            Token::Symbol(Symbol::Star) => Deref,
+            _ => anyhow::bail!(ParseError::Internal {
+                message: "Expected prefix unary operator".to_owned(),
+            }),
         };
         Ok((tokens, op))
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostfixUnaryOperator {
+    Increment,
+    Decrement,
+}
+
+impl PostfixUnaryOperator {
+    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
+        use PostfixUnaryOperator::*;
+        let (tokens, token) = next(tokens)?;
+        let op = match token {
+            Token::Symbol(Symbol::PlusPlus) => Increment,
+            Token::Symbol(Symbol::MinusMinus) => Decrement,
+            _ => anyhow::bail!(ParseError::Internal {
+                message: "Expected postfix unary operator".to_owned(),
+            }),
+        };
+        Ok((tokens, op))
+    }
+}
+
+/// Precedence of the conditional operator `?:`, which is right-associative.
+const CONDITIONAL_PRECEDENCE: usize = 13;
+
 /// [C Operator Precedence](https://en.cppreference.com/w/c/language/operator_precedence)
 trait Operator {
     fn precedence(&self) -> usize;
@@ -91,11 +218,18 @@ impl Operator for BinaryOperator {
     fn precedence(&self) -> usize {
         use BinaryOperator::*;
         match self {
-            Multiply | Divide => 3,
+            Multiply | Divide | Modulo => 3,
             Plus | Minus => 4,
+            ShiftLeft | ShiftRight => 5,
             Less | LessEqual | Greater | GreaterEqual => 6,
             Equal | NotEqual => 7,
-            Assign => 14,
+            BitAnd => 8,
+            BitXor => 9,
+            BitOr => 10,
+            LogicalAnd => 11,
+            LogicalOr => 12,
+            Assign | PlusAssign | MinusAssign | MultiplyAssign | DivideAssign | ModuloAssign
+            | ShiftLeftAssign | ShiftRightAssign | BitAndAssign | BitXorAssign | BitOrAssign => 14,
             Comma => 15,
         }
     }
@@ -103,9 +237,13 @@ impl Operator for BinaryOperator {
     fn right_associative(&self) -> bool {
         use BinaryOperator::*;
         match self {
-            Plus | Minus | Multiply | Divide | Less | LessEqual | Greater | GreaterEqual
-            | Equal | NotEqual | Comma => false,
-            Assign => true,
+            Plus | Minus | Multiply | Divide | Modulo | ShiftLeft | ShiftRight | Less
+            | LessEqual | Greater | GreaterEqual | Equal | NotEqual | BitAnd | BitXor | BitOr
+            | LogicalAnd | LogicalOr | Comma => false,
+            Assign | PlusAssign | MinusAssign | MultiplyAssign | DivideAssign | ModuloAssign
+            | ShiftLeftAssign | ShiftRightAssign | BitAndAssign | BitXorAssign | BitOrAssign => {
+                true
+            }
         }
     }
 }
@@ -114,139 +252,790 @@ impl Operator for PrefixUnaryOperator {
     fn precedence(&self) -> usize {
         use PrefixUnaryOperator::*;
         match self {
-            Plus | Minus | Not => 2,
+            Plus | Minus | Not | BitNot | Increment | Decrement | AddressOf | Deref => 2,
         }
     }
 
     fn right_associative(&self) -> bool {
         use PrefixUnaryOperator::*;
         match self {
-            Plus | Minus | Not => true,
+            Plus | Minus | Not | BitNot | Increment | Decrement | AddressOf | Deref => true,
+        }
+    }
+}
+
+impl Operator for PostfixUnaryOperator {
+    fn precedence(&self) -> usize {
+        use PostfixUnaryOperator::*;
+        match self {
+            Increment | Decrement => 1,
+        }
+    }
+
+    fn right_associative(&self) -> bool {
+        use PostfixUnaryOperator::*;
+        match self {
+            Increment | Decrement => false,
         }
     }
 }
 
 impl Expression {
-    fn parse_ref_or_call(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        let (tokens, Token::Identifier(identifier)) = next(tokens)? else {
-            anyhow::bail!("Expect identifier");
-        };
-        if let (mut tokens, Token::Symbol(Symbol::LeftParen)) = next(tokens)? {
-            let mut params = Vec::new();
-            match tokens {
-                [Token::Symbol(Symbol::RightParen), remain @ ..] => Ok((
-                    remain,
-                    Expression::RefOrCall(RefOrCall::FunctionCall(identifier, params)),
-                )),
-                _ => loop {
-                    let (remain, param) = Expression::parse(tokens)?;
-                    params.push(eval(param));
-                    tokens = match remain {
-                        [Token::Symbol(Symbol::RightParen), remain @ ..] => {
-                            break Ok((
-                                remain,
-                                Expression::RefOrCall(RefOrCall::FunctionCall(identifier, params)),
-                            ))
-                        }
-                        [Token::Symbol(Symbol::Comma), remain @ ..] => remain,
-                        _ => anyhow::bail!("Expected \")\" or \",\""),
-                    }
-                },
+    /// Location of the whole expression in the source file.
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Paren(_, span) | Expression::Constant(_, span) => *span,
+            Expression::Binary(lhs, _, rhs, _) => lhs.span().to(rhs.span()),
+            Expression::PrefixUnary(_, operand, span)
+            | Expression::Cast(_, operand, span)
+            | Expression::Sizeof(operand, span) => span.to(operand.span()),
+            Expression::PostfixUnary(_, operand, span) => operand.span().to(*span),
+            Expression::Conditional(condition, _, otherwise, _) => {
+                condition.span().to(otherwise.span())
             }
-        } else {
-            Ok((
-                tokens,
-                Expression::RefOrCall(RefOrCall::Variable(identifier)),
-            ))
+            Expression::RefOrCall(RefOrCall::Variable(_, span))
+            | Expression::SizeofType(_, span)
+            | Expression::Call(_, _, span)
+            | Expression::Subscript(_, _, span) => *span,
+            Expression::Member(object, _, span) | Expression::PointerMember(object, _, span) => {
+                object.span().to(*span)
+            }
+            Expression::Evaluate(expr) => expr.span(),
         }
     }
-    fn parse_constant(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        let (tokens, Token::Constant(constant)) = next(tokens)? else {
-            anyhow::bail!("Expect constant");
+
+    fn parse_identifier<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (remain, Token::Identifier(identifier)) = next(tokens)? else {
+            return Err(expected(ctx, tokens, "identifier"));
         };
-        Ok((tokens, Expression::Constant(constant)))
+        Ok((
+            remain,
+            Expression::RefOrCall(RefOrCall::Variable(identifier, ctx.span(tokens))),
+        ))
     }
-    fn parse_paren(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
+
+    /// Parse the arguments of a call, after the `(`.
+    fn parse_arguments<'a>(
+        mut tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Vec<Expression>)> {
+        let mut args = Vec::new();
+        if let [Token::Symbol(Symbol::RightParen), remain @ ..] = tokens {
+            return Ok((remain, args));
+        }
+        loop {
+            let (remain, arg) = Expression::parse_assignment(tokens, ctx)?;
+            args.push(eval(arg));
+            tokens = match remain {
+                [Token::Symbol(Symbol::RightParen), remain @ ..] => break Ok((remain, args)),
+                [Token::Symbol(Symbol::Comma), remain @ ..] => remain,
+                _ => return Err(expected(ctx, remain, "`)` or `,`")),
+            }
+        }
+    }
+
+    fn parse_constant<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (remain, Token::Constant(constant)) = next(tokens)? else {
+            return Err(expected(ctx, tokens, "constant"));
+        };
+        Ok((remain, Expression::Constant(constant, ctx.span(tokens))))
+    }
+    fn parse_paren<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let start = tokens;
         let (tokens, Token::Symbol(Symbol::LeftParen)) = next(tokens)? else {
-            anyhow::bail!("Expect left parentheses");
+            return Err(expected(ctx, tokens, "`(`"));
         };
-        let (tokens, inner) = Expression::parse(tokens)?;
+        let (tokens, inner) = Expression::parse(tokens, ctx)?;
         let (tokens, Token::Symbol(Symbol::RightParen)) = next(tokens)? else {
-            anyhow::bail!("Expect right parentheses");
+            return Err(expected(ctx, tokens, "`)`"));
         };
-        Ok((tokens, Expression::Paren(Box::new(inner))))
+        let span = ctx.span_between(start, tokens);
+        Ok((tokens, Expression::Paren(Box::new(inner), span)))
     }
 
-    fn parse_unary_operator(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        if let Ok((tokens, unary_op)) = PrefixUnaryOperator::parse(tokens) {
-            let (tokens, operand) = Expression::parse_unary_operator(tokens)?;
+    fn parse_unary_operator<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        if let [Token::Symbol(Symbol::LeftParen), after_paren @ ..] = tokens {
+            if ctx.starts_type_name(after_paren) {
+                return Expression::parse_cast(tokens, after_paren, ctx);
+            }
+        }
+        if let [Token::Keyword(Keyword::Sizeof), after_sizeof @ ..] = tokens {
+            return Expression::parse_sizeof(tokens, after_sizeof, ctx);
+        }
+        if let Ok((remain, unary_op)) = PrefixUnaryOperator::parse(tokens) {
+            let (remain, operand) = Expression::parse_unary_operator(remain, ctx)?;
+            let operand = match unary_op {
+                // the operand is modified or designated, not evaluated
+                PrefixUnaryOperator::Increment
+                | PrefixUnaryOperator::Decrement
+                | PrefixUnaryOperator::AddressOf => operand,
+                _ => eval(operand),
+            };
             Ok((
-                tokens,
-                Expression::PrefixUnary(unary_op, Box::new(eval(operand))),
+                remain,
+                Expression::PrefixUnary(unary_op, Box::new(operand), ctx.span(tokens)),
             ))
         } else {
-            Expression::parse_primary(tokens)
+            Expression::parse_postfix(tokens, ctx)
+        }
+    }
+
+    /// Parse a cast, whose type starts after the `(` at the start of `tokens`.
+    fn parse_cast<'a>(
+        tokens: &'a [Token],
+        after_paren: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (remain, ty) = Type::parse_type_name(after_paren, ctx)?;
+        let [Token::Symbol(Symbol::RightParen), remain @ ..] = remain else {
+            return Err(expected(ctx, remain, "`)`"));
+        };
+        let span = ctx.span_between(tokens, remain);
+        let (remain, operand) = Expression::parse_unary_operator(remain, ctx)?;
+        Ok((remain, Expression::Cast(ty, Box::new(eval(operand)), span)))
+    }
+
+    /// Parse `sizeof (type)` or `sizeof operand`, where `sizeof` is the
+    /// first token of `tokens`.
+    fn parse_sizeof<'a>(
+        tokens: &'a [Token],
+        after_sizeof: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        if let [Token::Symbol(Symbol::LeftParen), after_paren @ ..] = after_sizeof {
+            if ctx.starts_type_name(after_paren) {
+                let (remain, ty) = Type::parse_type_name(after_paren, ctx)?;
+                let [Token::Symbol(Symbol::RightParen), remain @ ..] = remain else {
+                    return Err(expected(ctx, remain, "`)`"));
+                };
+                let span = ctx.span_between(tokens, remain);
+                return Ok((remain, Expression::SizeofType(ty, span)));
+            }
+        }
+        let (remain, operand) = Expression::parse_unary_operator(after_sizeof, ctx)?;
+        let span = ctx.span(tokens);
+        Ok((remain, Expression::Sizeof(Box::new(operand), span)))
+    }
+
+    /// Parse a primary expression followed by any number of calls,
+    /// subscripts, member accesses, `++` and `--`.
+    fn parse_postfix<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let start = tokens;
+        let (mut tokens, mut expr) = Expression::parse_primary(tokens, ctx)?;
+        loop {
+            (tokens, expr) = match tokens {
+                [Token::Symbol(Symbol::LeftParen), remain @ ..] => {
+                    let (remain, args) = Expression::parse_arguments(remain, ctx)?;
+                    let span = ctx.span_between(start, remain);
+                    (remain, Expression::Call(Box::new(expr), args, span))
+                }
+                [Token::Symbol(Symbol::LeftBracket), remain @ ..] => {
+                    let (remain, index) = Expression::parse(remain, ctx)?;
+                    let [Token::Symbol(Symbol::RightBracket), remain @ ..] = remain else {
+                        return Err(expected(ctx, remain, "`]`"));
+                    };
+                    let span = ctx.span_between(start, remain);
+                    let index = Box::new(eval(index));
+                    (remain, Expression::Subscript(Box::new(expr), index, span))
+                }
+                [Token::Symbol(symbol @ (Symbol::Period | Symbol::Arrow)), remain @ ..] => {
+                    let [Token::Identifier(member), after_member @ ..] = remain else {
+                        return Err(expected(ctx, remain, "member name"));
+                    };
+                    let span = ctx.span(remain);
+                    let expr = Box::new(expr);
+                    let expr = match symbol {
+                        Symbol::Period => Expression::Member(expr, member.to_owned(), span),
+                        _ => Expression::PointerMember(expr, member.to_owned(), span),
+                    };
+                    (after_member, expr)
+                }
+                _ => match PostfixUnaryOperator::parse(tokens) {
+                    Ok((remain, postfix_op)) => {
+                        let span = ctx.span(tokens);
+                        (
+                            remain,
+                            Expression::PostfixUnary(postfix_op, Box::new(expr), span),
+                        )
+                    }
+                    Err(_) => break Ok((tokens, expr)),
+                },
+            };
         }
     }
 
-    fn parse_primary(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
+    fn parse_primary<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
         match tokens {
-            [Token::Identifier(_), ..] => Expression::parse_ref_or_call(tokens),
-            [Token::Constant(_), ..] => Expression::parse_constant(tokens),
-            [Token::Symbol(Symbol::LeftParen), ..] => Expression::parse_paren(tokens),
-            _ => anyhow::bail!("Expected expression"),
+            [Token::Identifier(_), ..] => Expression::parse_identifier(tokens, ctx),
+            [Token::Constant(_), ..] => Expression::parse_constant(tokens, ctx),
+            [Token::Symbol(Symbol::LeftParen), ..] => Expression::parse_paren(tokens, ctx),
+            _ => Err(expected(ctx, tokens, "expression")),
         }
     }
 
-    fn parse_rhs_of_binary(
-        mut tokens: &[Token],
+    /// Parse the operators following `lhs` that bind no looser than
+    /// `max_precedence` (precedence climbing).
+    fn parse_rhs_of_binary<'a>(
+        mut tokens: &'a [Token],
+        ctx: &mut ParseContext,
         mut lhs: Expression,
         max_precedence: usize,
-    ) -> anyhow::Result<(&[Token], Self)> {
+    ) -> anyhow::Result<(&'a [Token], Self)> {
         loop {
+            if let [Token::Symbol(Symbol::Question), remain @ ..] = tokens {
+                if CONDITIONAL_PRECEDENCE > max_precedence {
+                    break Ok((tokens, lhs));
+                }
+                let op_span = ctx.span(tokens);
+                let (remain, then) = Expression::parse(remain, ctx)?;
+                let [Token::Symbol(Symbol::Colon), remain @ ..] = remain else {
+                    return Err(expected(ctx, remain, "`:`"));
+                };
+                let (remain, otherwise) =
+                    Expression::parse_binary_operator(remain, ctx, CONDITIONAL_PRECEDENCE)?;
+                lhs = Expression::Conditional(
+                    Box::new(eval(lhs)),
+                    Box::new(eval(then)),
+                    Box::new(eval(otherwise)),
+                    op_span,
+                );
+                tokens = remain;
+                continue;
+            }
             let Ok((remain, bin_op)) = BinaryOperator::parse(tokens) else {
                 break Ok((tokens, lhs));
             };
-            let right_assiciative = bin_op.right_associative();
+            let op_span = ctx.span(tokens);
             let precedence = bin_op.precedence();
             if precedence > max_precedence {
                 break Ok((tokens, lhs));
             }
-            let (remain, rhs) = Expression::parse_primary(remain)?;
-            let mut rhs = eval(rhs);
-            lhs = match bin_op {
-                BinaryOperator::Assign => lhs,
-                _ => eval(lhs),
-            };
-            let Ok((_, next_bin_op)) = BinaryOperator::parse(remain) else {
-                let expr = Expression::Binary(Box::new(lhs), bin_op, Box::new(rhs));
-                break Ok((remain, expr));
+            let (remain, rhs) = Expression::parse_unary_operator(remain, ctx)?;
+            // operators binding tighter belong to the right operand, as well as
+            // the same operator if it is right-associative
+            let rhs_max_precedence = if bin_op.right_associative() {
+                precedence
+            } else {
+                precedence - 1
             };
-            let next_precedence = next_bin_op.precedence();
-            if precedence > next_precedence || (precedence == next_precedence && right_assiciative)
-            {
-                let next_max_precedence = if right_assiciative {
-                    precedence
-                } else {
-                    precedence - 1
+            let (remain, rhs) =
+                Expression::parse_rhs_of_binary(remain, ctx, rhs, rhs_max_precedence)?;
+            if !bin_op.is_assignment() {
+                lhs = eval(lhs);
+            }
+            lhs = Expression::Binary(Box::new(lhs), bin_op, Box::new(eval(rhs)), op_span);
+            tokens = remain;
+        }
+    }
+
+    fn parse_binary_operator<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        max_precedence: usize,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (tokens, lhs) = Expression::parse_unary_operator(tokens, ctx)?;
+        Expression::parse_rhs_of_binary(tokens, ctx, lhs, max_precedence)
+    }
+
+    /// Parse an expression, including the comma operator.
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        Expression::parse_binary_operator(tokens, ctx, BinaryOperator::Comma.precedence())
+    }
+
+    /// Parse an expression without a top-level comma operator, where commas
+    /// separate items, e.g. function arguments.
+    pub fn parse_assignment<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        Expression::parse_binary_operator(tokens, ctx, BinaryOperator::Assign.precedence())
+    }
+
+    /// Parse a conditional expression, i.e. without assignments or commas,
+    /// as in a constant expression.
+    pub fn parse_conditional<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        Expression::parse_binary_operator(tokens, ctx, CONDITIONAL_PRECEDENCE)
+    }
+}
+
+impl std::fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use BinaryOperator::*;
+        let symbol = match self {
+            Plus => "+",
+            Minus => "-",
+            Multiply => "*",
+            Divide => "/",
+            Modulo => "%",
+            ShiftLeft => "<<",
+            ShiftRight => ">>",
+            Less => "<",
+            LessEqual => "<=",
+            Greater => ">",
+            GreaterEqual => ">=",
+            Equal => "==",
+            NotEqual => "!=",
+            BitAnd => "&",
+            BitXor => "^",
+            BitOr => "|",
+            LogicalAnd => "&&",
+            LogicalOr => "||",
+            Assign => "=",
+            PlusAssign => "+=",
+            MinusAssign => "-=",
+            MultiplyAssign => "*=",
+            DivideAssign => "/=",
+            ModuloAssign => "%=",
+            ShiftLeftAssign => "<<=",
+            ShiftRightAssign => ">>=",
+            BitAndAssign => "&=",
+            BitXorAssign => "^=",
+            BitOrAssign => "|=",
+            Comma => ",",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl std::fmt::Display for PrefixUnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PrefixUnaryOperator::*;
+        let symbol = match self {
+            Plus => "+",
+            Minus => "-",
+            Not => "!",
+            BitNot => "~",
+            Increment => "++",
+            Decrement => "--",
+            AddressOf => "&",
+            Deref => "*",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl std::fmt::Display for PostfixUnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostfixUnaryOperator::Increment => write!(f, "++"),
+            PostfixUnaryOperator::Decrement => write!(f, "--"),
+        }
+    }
+}
+
+/// Source text of an expression. Parentheses are written where the source
+/// has them, which is enough to keep its grouping.
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Evaluate(expr) => write!(f, "{expr}"),
+            Expression::Paren(expr, _) => write!(f, "({expr})"),
+            Expression::Binary(lhs, BinaryOperator::Comma, rhs, _) => write!(f, "{lhs}, {rhs}"),
+            Expression::Binary(lhs, op, rhs, _) => write!(f, "{lhs} {op} {rhs}"),
+            Expression::PrefixUnary(op, operand, _) => {
+                let operand = operand.to_string();
+                // `- -x` is not `--x`
+                match operand.starts_with(['+', '-', '&']) {
+                    true => write!(f, "{op} {operand}"),
+                    false => write!(f, "{op}{operand}"),
+                }
+            }
+            Expression::PostfixUnary(op, operand, _) => write!(f, "{operand}{op}"),
+            Expression::Cast(ty, operand, _) => write!(f, "({ty}){operand}"),
+            Expression::Sizeof(operand, _) => write!(f, "sizeof {operand}"),
+            Expression::SizeofType(ty, _) => write!(f, "sizeof({ty})"),
+            Expression::Conditional(condition, then, otherwise, _) => {
+                write!(f, "{condition} ? {then} : {otherwise}")
+            }
+            Expression::RefOrCall(RefOrCall::Variable(name, _)) => write!(f, "{name}"),
+            Expression::Call(callee, args, _) => {
+                let args: Vec<_> = args.iter().map(Expression::to_string).collect();
+                write!(f, "{callee}({})", args.join(", "))
+            }
+            Expression::Subscript(array, index, _) => write!(f, "{array}[{index}]"),
+            Expression::Member(object, member, _) => write!(f, "{object}.{member}"),
+            Expression::PointerMember(pointer, member, _) => write!(f, "{pointer}->{member}"),
+            Expression::Constant(constant, _) => write!(f, "{constant}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::lexical_analysis::extract_tokens;
+
+    /// Operators at the top of the parse tree of `code` and of its right operand.
+    fn top_operators(code: &str) -> (BinaryOperator, BinaryOperator) {
+        let tokens = extract_tokens(code).unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        let (remain, expr) = Expression::parse(&tokens.tokens, &mut ctx).unwrap();
+        assert!(matches!(remain, [Token::Symbol(Symbol::Semicolon), ..]));
+        let Expression::Binary(_, top, rhs, _) = expr else {
+            panic!("expected binary expression: {expr:?}");
+        };
+        let Expression::Evaluate(rhs) = *rhs else {
+            panic!("expected evaluated operand: {rhs:?}");
+        };
+        let Expression::Binary(_, right, _, _) = *rhs else {
+            panic!("expected binary right operand: {rhs:?}");
+        };
+        (top, right)
+    }
+
+    #[test]
+    fn logical_and_binds_tighter_than_or() {
+        assert!(matches!(
+            top_operators("a || b && c == d;"),
+            (BinaryOperator::LogicalOr, BinaryOperator::LogicalAnd)
+        ));
+    }
+
+    #[test]
+    fn bitwise_operator_precedence() {
+        assert!(matches!(
+            top_operators("a | b ^ c;"),
+            (BinaryOperator::BitOr, BinaryOperator::BitXor)
+        ));
+        assert!(matches!(
+            top_operators("a ^ b & c;"),
+            (BinaryOperator::BitXor, BinaryOperator::BitAnd)
+        ));
+        assert!(matches!(
+            top_operators("a & b == c;"),
+            (BinaryOperator::BitAnd, BinaryOperator::Equal)
+        ));
+        assert!(matches!(
+            top_operators("a < b << c;"),
+            (BinaryOperator::Less, BinaryOperator::ShiftLeft)
+        ));
+        assert!(matches!(
+            top_operators("a >> b + c % d;"),
+            (BinaryOperator::ShiftRight, BinaryOperator::Plus)
+        ));
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert!(matches!(
+            top_operators("a = b += c;"),
+            (BinaryOperator::Assign, BinaryOperator::PlusAssign)
+        ));
+        assert!(matches!(
+            top_operators("a <<= b - c;"),
+            (BinaryOperator::ShiftLeftAssign, BinaryOperator::Minus)
+        ));
+    }
+
+    #[test]
+    fn comma_operator_is_separate_from_arguments() {
+        assert!(matches!(
+            top_operators("a = 1, b = 2;"),
+            (BinaryOperator::Comma, BinaryOperator::Assign)
+        ));
+        let tokens = extract_tokens("f((a, b), c);").unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        let (_, expr) = Expression::parse(&tokens.tokens, &mut ctx).unwrap();
+        let Expression::Call(_, args, _) = expr else {
+            panic!("expected function call: {expr:?}");
+        };
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn postfix_operators_chain_left_to_right() {
+        let parse = |code: &str| {
+            let tokens = extract_tokens(code).unwrap();
+            let mut ctx = ParseContext::new(&tokens.spans);
+            let (_, expr) = Expression::parse(&tokens.tokens, &mut ctx).unwrap();
+            parenthesize(&expr)
+        };
+        assert_eq!(parse("f(x)(y, z);"), "((f(x))(y, z))");
+        assert_eq!(parse("a[i].f->g++;"), "((((a[i]).f)->g) ++)");
+        assert_eq!(parse("(*fp)(x);"), "((* fp)(x))");
+        assert_eq!(parse("-a[i + 1];"), "(- (a[(i + 1)]))");
+    }
+
+    #[test]
+    fn parenthesized_typedef_name_is_cast() {
+        let tokens = extract_tokens(
+            "(T)-x; (x)(y); (T)(y); (long)*p; (T (*)[2])p; sizeof (T) - 1; sizeof (x) - 1;",
+        )
+        .unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        ctx.declare("T", true);
+        let mut remain = &tokens.tokens[..];
+        let mut parsed = Vec::new();
+        while !matches!(remain, [Token::End, ..]) {
+            let (rest, expr) = Expression::parse(remain, &mut ctx).unwrap();
+            parsed.push(parenthesize(&expr));
+            remain = &rest[1..];
+        }
+        assert_eq!(
+            parsed,
+            [
+                "((T) (- x))",
+                "(x(y))",
+                "((T) y)",
+                "((long) (* p))",
+                "((T (*)[2]) p)",
+                "((sizeof (T)) - 1)",
+                "((sizeof x) - 1)",
+            ]
+        );
+        ctx.in_scope(|ctx| {
+            ctx.declare("T", false);
+            let tokens = extract_tokens("(T)(y);").unwrap();
+            let (_, expr) = Expression::parse(&tokens.tokens, ctx).unwrap();
+            assert_eq!(parenthesize(&expr), "(T(y))");
+        });
+    }
+
+    #[test]
+    fn conditional_is_right_associative() {
+        let tokens = extract_tokens("x = a + b ? c : d ? e : f;").unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        let (_, expr) = Expression::parse(&tokens.tokens, &mut ctx).unwrap();
+        let Expression::Binary(_, BinaryOperator::Assign, rhs, _) = expr else {
+            panic!("expected assignment: {expr:?}");
+        };
+        let Expression::Evaluate(rhs) = *rhs else {
+            panic!("expected evaluated operand: {rhs:?}");
+        };
+        let Expression::Conditional(condition, _, otherwise, _) = *rhs else {
+            panic!("expected conditional: {rhs:?}");
+        };
+        assert!(matches!(
+            *condition,
+            Expression::Evaluate(ref condition)
+                if matches!(**condition, Expression::Binary(_, BinaryOperator::Plus, _, _))
+        ));
+        assert!(matches!(
+            *otherwise,
+            Expression::Evaluate(ref otherwise)
+                if matches!(**otherwise, Expression::Conditional(..))
+        ));
+    }
+
+    /// Binary operators with their precedence and associativity, independent
+    /// of the parser's tables.
+    const BINARY_OPERATORS: &[(&str, usize, bool)] = &[
+        ("*", 3, false),
+        ("/", 3, false),
+        ("%", 3, false),
+        ("+", 4, false),
+        ("-", 4, false),
+        ("<<", 5, false),
+        (">>", 5, false),
+        ("<", 6, false),
+        ("<=", 6, false),
+        (">", 6, false),
+        (">=", 6, false),
+        ("==", 7, false),
+        ("!=", 7, false),
+        ("&", 8, false),
+        ("^", 9, false),
+        ("|", 10, false),
+        ("&&", 11, false),
+        ("||", 12, false),
+        ("=", 14, true),
+        ("+=", 14, true),
+        ("<<=", 14, true),
+        ("|=", 14, true),
+        (",", 15, false),
+    ];
+    const PREFIX_OPERATORS: &[&str] = &["-", "+", "!", "~", "++", "--"];
+    const POSTFIX_OPERATORS: &[&str] = &["++", "--"];
+
+    /// Reference printer: every operation is parenthesized.
+    fn parenthesize(expr: &Expression) -> String {
+        match expr {
+            Expression::Evaluate(expr) | Expression::Paren(expr, _) => parenthesize(expr),
+            Expression::Binary(lhs, op, rhs, _) => {
+                let op = match op {
+                    BinaryOperator::Multiply => "*",
+                    BinaryOperator::Divide => "/",
+                    BinaryOperator::Modulo => "%",
+                    BinaryOperator::Plus => "+",
+                    BinaryOperator::Minus => "-",
+                    BinaryOperator::ShiftLeft => "<<",
+                    BinaryOperator::ShiftRight => ">>",
+                    BinaryOperator::Less => "<",
+                    BinaryOperator::LessEqual => "<=",
+                    BinaryOperator::Greater => ">",
+                    BinaryOperator::GreaterEqual => ">=",
+                    BinaryOperator::Equal => "==",
+                    BinaryOperator::NotEqual => "!=",
+                    BinaryOperator::BitAnd => "&",
+                    BinaryOperator::BitXor => "^",
+                    BinaryOperator::BitOr => "|",
+                    BinaryOperator::LogicalAnd => "&&",
+                    BinaryOperator::LogicalOr => "||",
+                    BinaryOperator::Assign => "=",
+                    BinaryOperator::PlusAssign => "+=",
+                    BinaryOperator::MinusAssign => "-=",
+                    BinaryOperator::MultiplyAssign => "*=",
+                    BinaryOperator::DivideAssign => "/=",
+                    BinaryOperator::ModuloAssign => "%=",
+                    BinaryOperator::ShiftLeftAssign => "<<=",
+                    BinaryOperator::ShiftRightAssign => ">>=",
+                    BinaryOperator::BitAndAssign => "&=",
+                    BinaryOperator::BitXorAssign => "^=",
+                    BinaryOperator::BitOrAssign => "|=",
+                    BinaryOperator::Comma => ",",
                 };
-                let (remain, new_rhs) =
-                    Expression::parse_rhs_of_binary(remain, rhs, next_max_precedence)?;
-                rhs = eval(new_rhs);
-                tokens = remain;
-            } else {
-                tokens = remain;
+                format!("({} {op} {})", parenthesize(lhs), parenthesize(rhs))
+            }
+            Expression::PrefixUnary(op, operand, _) => {
+                let op = match op {
+                    PrefixUnaryOperator::Minus => "-",
+                    PrefixUnaryOperator::Plus => "+",
+                    PrefixUnaryOperator::Not => "!",
+                    PrefixUnaryOperator::BitNot => "~",
+                    PrefixUnaryOperator::Increment => "++",
+                    PrefixUnaryOperator::Decrement => "--",
+                    PrefixUnaryOperator::AddressOf => "&",
+                    PrefixUnaryOperator::Deref => "*",
+                };
+                format!("({op} {})", parenthesize(operand))
+            }
+            Expression::Cast(ty, operand, _) => format!("(({ty}) {})", parenthesize(operand)),
+            Expression::Sizeof(operand, _) => format!("(sizeof {})", parenthesize(operand)),
+            Expression::SizeofType(ty, _) => format!("(sizeof ({ty}))"),
+            Expression::PostfixUnary(op, operand, _) => {
+                let op = match op {
+                    PostfixUnaryOperator::Increment => "++",
+                    PostfixUnaryOperator::Decrement => "--",
+                };
+                format!("({} {op})", parenthesize(operand))
+            }
+            Expression::Conditional(condition, then, otherwise, _) => format!(
+                "({} ? {} : {})",
+                parenthesize(condition),
+                parenthesize(then),
+                parenthesize(otherwise)
+            ),
+            Expression::Constant(constant, _) => constant.to_string(),
+            Expression::RefOrCall(RefOrCall::Variable(name, _)) => name.to_string(),
+            Expression::Call(callee, args, _) => {
+                let args: Vec<_> = args.iter().map(parenthesize).collect();
+                format!("({}({}))", parenthesize(callee), args.join(", "))
+            }
+            Expression::Subscript(array, index, _) => {
+                format!("({}[{}])", parenthesize(array), parenthesize(index))
+            }
+            Expression::Member(object, member, _) => {
+                format!("({}.{member})", parenthesize(object))
+            }
+            Expression::PointerMember(pointer, member, _) => {
+                format!("({}->{member})", parenthesize(pointer))
             }
-            lhs = Expression::Binary(Box::new(lhs), bin_op, Box::new(rhs));
         }
     }
 
-    fn parse_binary_operator(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        let (tokens, lhs) = Expression::parse_unary_operator(tokens)?;
-        Expression::parse_rhs_of_binary(tokens, lhs, BinaryOperator::Comma.precedence())
+    /// An operand with prefix and postfix operators, as source text and
+    /// fully parenthesized. Postfix operators bind tighter than prefix ones.
+    fn operand() -> impl Strategy<Value = (String, String)> {
+        let atom = prop_oneof![
+            prop::sample::select(vec!["a", "b", "c"]).prop_map(str::to_string),
+            (0..100u8).prop_map(|value| value.to_string()),
+        ];
+        (
+            prop::collection::vec(prop::sample::select(PREFIX_OPERATORS), 0..3),
+            atom,
+            prop::collection::vec(prop::sample::select(POSTFIX_OPERATORS), 0..2),
+        )
+            .prop_map(|(prefix, atom, postfix)| {
+                let mut source = atom.clone();
+                let mut expected = atom;
+                for op in postfix {
+                    source = format!("{source} {op}");
+                    expected = format!("({expected} {op})");
+                }
+                for op in prefix.into_iter().rev() {
+                    source = format!("{op} {source}");
+                    expected = format!("({op} {expected})");
+                }
+                (source, expected)
+            })
     }
 
-    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        Expression::parse_binary_operator(tokens)
+    /// Reference grouping of `first op operand op operand ...` by operator
+    /// precedence (shunting-yard).
+    fn reference_grouping(first: String, rest: &[(usize, String)]) -> String {
+        fn reduce(operands: &mut Vec<String>, op: usize) {
+            let rhs = operands.pop().unwrap();
+            let lhs = operands.pop().unwrap();
+            operands.push(format!("({lhs} {} {rhs})", BINARY_OPERATORS[op].0));
+        }
+        let mut operands = vec![first];
+        let mut operators: Vec<usize> = Vec::new();
+        for (op, operand) in rest {
+            let (_, precedence, right_associative) = BINARY_OPERATORS[*op];
+            while let Some(&top) = operators.last() {
+                let top_precedence = BINARY_OPERATORS[top].1;
+                if top_precedence < precedence
+                    || (top_precedence == precedence && !right_associative)
+                {
+                    operators.pop();
+                    reduce(&mut operands, top);
+                } else {
+                    break;
+                }
+            }
+            operators.push(*op);
+            operands.push(operand.clone());
+        }
+        while let Some(top) = operators.pop() {
+            reduce(&mut operands, top);
+        }
+        operands.pop().unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn parse_tree_respects_precedence(
+            first in operand(),
+            rest in prop::collection::vec((0..BINARY_OPERATORS.len(), operand()), 0..6),
+        ) {
+            let mut source = first.0;
+            for (op, (operand, _)) in &rest {
+                source = format!("{source} {} {operand}", BINARY_OPERATORS[*op].0);
+            }
+            let rest: Vec<_> = rest.into_iter().map(|(op, (_, expected))| (op, expected)).collect();
+            let expected = reference_grouping(first.1, &rest);
+
+            let tokens = extract_tokens(&format!("{source};")).unwrap();
+            let mut ctx = ParseContext::new(&tokens.spans);
+            let (remain, expr) = Expression::parse(&tokens.tokens, &mut ctx).unwrap();
+            prop_assert!(matches!(remain, [Token::Symbol(Symbol::Semicolon), ..]));
+            prop_assert_eq!(parenthesize(&expr), expected, "source: {}", source);
+        }
     }
 }
@@ -0,0 +1,54 @@
+use crate::{
+    diagnostics::Span,
+    lexical_analysis::{Symbol, Token},
+};
+
+use super::{
+    expected,
+    expression::{eval, Expression},
+    ParseContext,
+};
+
+/// The initial value of a declared object.
+#[derive(Debug)]
+pub enum Initializer {
+    Expression(Expression),
+    /// `{ a, b, ... }`, with the span of the braces and everything in them.
+    List(Vec<Initializer>, Span),
+}
+
+impl Initializer {
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let [Token::Symbol(Symbol::LeftBrace), after_brace @ ..] = tokens else {
+            let (remain, expr) = Expression::parse_assignment(tokens, ctx)?;
+            return Ok((remain, Initializer::Expression(eval(expr))));
+        };
+        let mut remain = after_brace;
+        let mut items = Vec::new();
+        // a trailing comma is allowed, and so is an empty list
+        while !matches!(remain, [Token::Symbol(Symbol::RightBrace), ..]) {
+            let (after_item, item) = Initializer::parse(remain, ctx)?;
+            items.push(item);
+            remain = match after_item {
+                [Token::Symbol(Symbol::Comma), after_comma @ ..] => after_comma,
+                [Token::Symbol(Symbol::RightBrace), ..] => after_item,
+                _ => return Err(expected(ctx, after_item, "`,` or `}`")),
+            };
+        }
+        let remain = &remain[1..];
+        Ok((
+            remain,
+            Initializer::List(items, ctx.span_between(tokens, remain)),
+        ))
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Initializer::Expression(expr) => expr.span(),
+            Initializer::List(_, span) => *span,
+        }
+    }
+}
@@ -1,25 +1,353 @@
-use crate::{lexical_analysis::Token, syntax_analysis::translation_unit::TranslationUnit};
+use std::collections::HashMap;
+
+use crate::{
+    diagnostics::Span,
+    lexical_analysis::{Keyword, Symbol, Token, Tokens},
+    syntax_analysis::translation_unit::TranslationUnit,
+};
 
 pub mod block_statement;
 pub mod declaration;
+pub mod declarator;
+mod error;
 pub mod expression;
+pub mod initializer;
 pub mod parameter_definition;
 pub mod statement;
 pub mod translation_unit;
 pub mod types;
 
+pub use error::ParseError;
+
+/// State shared by the parsing functions.
+///
+/// Every token slice handed to a parsing function is a suffix of the token
+/// list, so the span of its first token can be found from its length.
+pub struct ParseContext<'s> {
+    spans: &'s [Span],
+    /// Syntax errors recovered from so far.
+    errors: Vec<ParseError>,
+    /// Ordinary identifiers declared in each enclosing scope, innermost
+    /// last, and whether each is a typedef name. An identifier is a type
+    /// unless an inner declaration of it shadows the typedef.
+    scopes: Vec<HashMap<String, bool>>,
+}
+
+impl<'s> ParseContext<'s> {
+    pub fn new(spans: &'s [Span]) -> Self {
+        ParseContext {
+            spans,
+            errors: Vec::new(),
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    /// Run `f` in a new scope for identifiers.
+    fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// Declare an ordinary identifier in the current scope.
+    fn declare(&mut self, name: &str, typedef: bool) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.insert(name.to_owned(), typedef);
+    }
+
+    /// Whether `name` names a type where it is used.
+    fn is_typedef_name(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Whether `tokens` start with a type, as in a cast.
+    fn starts_type_name(&self, tokens: &[Token]) -> bool {
+        match tokens {
+            [Token::Keyword(keyword), ..] => keyword.is_type_specifier(),
+            [Token::Identifier(name), ..] => self.is_typedef_name(name),
+            _ => false,
+        }
+    }
+
+    /// Whether `tokens` start with a declaration rather than a statement. A
+    /// typedef name followed by `:` is a label.
+    fn starts_declaration(&self, tokens: &[Token]) -> bool {
+        match tokens {
+            [Token::Keyword(keyword), ..] => keyword.starts_declaration(),
+            [Token::Identifier(_), Token::Symbol(Symbol::Colon), ..] => false,
+            _ => self.starts_type_name(tokens),
+        }
+    }
+
+    /// Span of the first token in `tokens`.
+    fn span(&self, tokens: &[Token]) -> Span {
+        let index = self.spans.len() - tokens.len();
+        match self.spans.get(index) {
+            Some(span) => *span,
+            None => self.spans.last().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Span of the tokens consumed between `before` and `after`.
+    fn span_between(&self, before: &[Token], after: &[Token]) -> Span {
+        let start = self.spans.len() - before.len();
+        let end = self.spans.len() - after.len();
+        if start >= end {
+            return self.span(before);
+        }
+        self.spans[start].to(self.spans[end - 1])
+    }
+
+    /// Record a syntax error found while parsing `tokens`, then skip to a
+    /// point where parsing can resume (panic-mode recovery).
+    ///
+    /// Returns the remaining tokens and the span of what was skipped, to be
+    /// kept as an error node in the syntax tree. Internal errors are
+    /// compiler bugs and are passed through.
+    fn recover<'a>(
+        &mut self,
+        err: anyhow::Error,
+        tokens: &'a [Token],
+    ) -> anyhow::Result<(&'a [Token], Span)> {
+        let error = err.downcast::<ParseError>()?;
+        if let ParseError::Internal { .. } = error {
+            anyhow::bail!(error);
+        }
+        let error_start = error.span().start;
+        let first = self.spans.len() - tokens.len();
+        let error_index = self.spans[first..]
+            .iter()
+            .position(|span| span.start >= error_start)
+            .unwrap_or(tokens.len() - 1);
+        self.report(error);
+        let remain = synchronize(&tokens[error_index..], error_index == 0);
+        Ok((remain, self.span_between(tokens, remain)))
+    }
+
+    /// Record a syntax error, unless it is likely caused by a previous one.
+    fn report(&mut self, error: ParseError) {
+        let start = error.span().start;
+        let end_of_file = self.spans.last().map(|span| span.start);
+        let cascade = self.errors.iter().any(|reported| {
+            // the same token reported again, or an unexpected end of file
+            // after skipping tokens (e.g. a `}` swallowed by recovery)
+            reported.span().start == start || Some(start) == end_of_file
+        });
+        if cascade {
+            tracing::debug!("Suppressed cascading error: {error}");
+        } else {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// Skip tokens until parsing can resume: after a `;` or a `{ ... }` group,
+/// or before a `}`, the start of a declaration or statement, or the end of
+/// file. Consumes at least one token if `must_advance` is set.
+fn synchronize(mut tokens: &[Token], mut must_advance: bool) -> &[Token] {
+    let mut depth: usize = 0;
+    loop {
+        match tokens {
+            [] | [Token::End, ..] => break tokens,
+            [Token::Symbol(Symbol::RightBrace), ..] if depth == 0 && !must_advance => {
+                break tokens;
+            }
+            [Token::Keyword(keyword), ..]
+                if keyword.starts_declaration() && depth == 0 && !must_advance =>
+            {
+                break tokens;
+            }
+            [Token::Keyword(
+                Keyword::If
+                | Keyword::While
+                | Keyword::Do
+                | Keyword::For
+                | Keyword::Break
+                | Keyword::Continue
+                | Keyword::Switch
+                | Keyword::Case
+                | Keyword::Default
+                | Keyword::Goto
+                | Keyword::Return,
+            ), ..]
+                if depth == 0 && !must_advance =>
+            {
+                break tokens;
+            }
+            [Token::Symbol(Symbol::Semicolon), remain @ ..] if depth == 0 => break remain,
+            [Token::Symbol(Symbol::LeftBrace), remain @ ..] => {
+                depth += 1;
+                tokens = remain;
+            }
+            [Token::Symbol(Symbol::RightBrace), remain @ ..] => {
+                tokens = remain;
+                match depth {
+                    0 => {}
+                    1 => break tokens,
+                    _ => depth -= 1,
+                }
+            }
+            [_, remain @ ..] => tokens = remain,
+        }
+        must_advance = false;
+    }
+}
+
 fn next(tokens: &[Token]) -> anyhow::Result<(&[Token], Token)> {
     match tokens {
-        [] => anyhow::bail!("Expected token"),
+        [] => anyhow::bail!(ParseError::Internal {
+            message: "Expected token".to_owned(),
+        }),
         [token, remain @ ..] => Ok((remain, token.to_owned())),
     }
 }
 
-pub fn parse(tokens: &[Token]) -> anyhow::Result<TranslationUnit> {
-    let (tokens, unit) = TranslationUnit::parse(tokens)?;
+/// Error for an unexpected token at the start of `tokens`.
+fn expected(ctx: &ParseContext, tokens: &[Token], expected: &str) -> anyhow::Error {
+    let found = match tokens.first() {
+        None | Some(Token::End) => "end of file".to_string(),
+        Some(token) => format!("`{token}`"),
+    };
+    ParseError::UnexpectedToken {
+        expected: expected.to_string(),
+        found,
+        span: ctx.span(tokens),
+    }
+    .into()
+}
+
+/// Parse a translation unit, reporting every syntax error found.
+///
+/// Syntax errors are all recovered from, so an error that stops the parse
+/// is a bug in the parser, and is returned as [`ParseError::Internal`].
+pub fn parse(tokens: &Tokens) -> Result<TranslationUnit, ParseError> {
+    let mut ctx = ParseContext::new(&tokens.spans);
+    let (tokens, unit) = TranslationUnit::parse(&tokens.tokens, &mut ctx).map_err(|err| {
+        match err.downcast::<ParseError>() {
+            Ok(err @ ParseError::Internal { .. }) => err,
+            Ok(err) => ParseError::Internal {
+                message: format!("Unrecovered syntax error: {err}"),
+            },
+            Err(err) => ParseError::Internal {
+                message: err.to_string(),
+            },
+        }
+    })?;
     debug_assert!(
         tokens.is_empty(),
         "Token not empty after parsing translation unit"
     );
-    Ok(unit)
+    match ctx.errors.len() {
+        0 => Ok(unit),
+        1 => Err(ctx.errors.remove(0)),
+        _ => Err(ParseError::Multiple(ctx.errors)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical_analysis::extract_tokens;
+    use declaration::Declaration;
+
+    fn syntax_errors(code: &str) -> Vec<String> {
+        let tokens = extract_tokens(code).unwrap();
+        match parse(&tokens) {
+            Ok(_) => Vec::new(),
+            Err(err) => err
+                .to_diagnostics()
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn report_every_syntax_error() {
+        let code = "int f(int a) {\n\
+                        int b = ;\n\
+                        if (a +) { b = 1; }\n\
+                        return b\n\
+                    }\n\
+                    junk;\n\
+                    int g(void) { return 1 + ; }\n";
+        assert_eq!(
+            syntax_errors(code),
+            vec![
+                "expected expression, found `;`",
+                "expected expression, found `)`",
+                "expected `;`, found `}`",
+                "expected declaration, found `junk`",
+                "expected expression, found `;`",
+            ]
+        );
+    }
+
+    #[test]
+    fn suppress_cascading_errors() {
+        // the missing `}` is not reported again at the end of file
+        assert_eq!(
+            syntax_errors("int f(void) {\n    return 1 +;\n"),
+            vec!["expected expression, found `;`"]
+        );
+        assert_eq!(
+            syntax_errors("int f(void) {\n    return 1;\n"),
+            vec!["expected `}`, found end of file"]
+        );
+        assert!(syntax_errors("int f(void) { if (1) { return 1; } return 0; }").is_empty());
+    }
+
+    #[test]
+    fn parser_bugs_are_internal_errors() {
+        // without the end of file token, the parser runs out of tokens
+        let mut tokens = extract_tokens("int f(void) { return 0; }").unwrap();
+        tokens.tokens.pop();
+        tokens.spans.pop();
+        let err = parse(&tokens).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::Internal {
+                message: "Expected token".to_owned()
+            }
+        );
+        assert_eq!(err.code(), "E0104");
+    }
+
+    #[test]
+    fn declarators_share_the_specifiers() {
+        let tokens = extract_tokens("typedef int A, *B; B a, b[2] = {0}, f(A);").unwrap();
+        let TranslationUnit(decls) = parse(&tokens).unwrap();
+        let declared: Vec<_> = decls
+            .iter()
+            .map(|decl| match decl {
+                Declaration::Typedef(ty, name, _) | Declaration::Variable(ty, name, _, _) => {
+                    format!("{name}: {ty}")
+                }
+                Declaration::Function(ty, name, params, None, _) => {
+                    format!("{name}: {ty} ({} parameter)", params.len())
+                }
+                decl => panic!("unexpected declaration {decl:?}"),
+            })
+            .collect();
+        assert_eq!(
+            declared,
+            [
+                "A: int",
+                "B: int *",
+                "a: B",
+                "b: B[2]",
+                "f: B (1 parameter)"
+            ]
+        );
+        assert_eq!(
+            syntax_errors("int a, f(void) { return 0; }"),
+            vec!["expected `,` or `;`, found `{`"]
+        );
+    }
 }
@@ -1,19 +1,66 @@
-use crate::{lexical_analysis::Token, syntax_analysis::next};
+use crate::{
+    diagnostics::Span,
+    lexical_analysis::{Keyword, Symbol, Token},
+    syntax_analysis::next,
+};
 
-use super::types::Type;
+use super::{declarator::Declarator, expected, types::Type, ParseContext, ParseError};
 
+/// A parameter with its type as written, and its name unless it is
+/// abstract as in `int f(char *)`. Arrays and functions are adjusted to
+/// pointers by semantic analysis.
 #[derive(Debug)]
-pub struct ParameterDefinition(pub Type, pub String);
+pub struct ParameterDefinition(pub Type, pub Option<String>, pub Span);
 
 impl ParameterDefinition {
-    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        let (tokens, param_type) = Type::parse(tokens)?;
-        if matches!(param_type, Type::Void) {
-            anyhow::bail!("Parameter cannot be void");
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (remain, param_type) = Type::parse(tokens, ctx)?;
+        let (remain, declarator) = Declarator::parse_parameter(remain, ctx, param_type)?;
+        if matches!(declarator.ty, Type::Void) {
+            anyhow::bail!(ParseError::VoidParameter {
+                span: ctx.span(tokens)
+            });
         }
-        let (tokens, Token::Identifier(param_name)) = next(tokens)? else {
-            anyhow::bail!("Expected identifier");
+        let span = match &declarator.name {
+            Some(name) => {
+                ctx.declare(name, false);
+                declarator.span
+            }
+            None => ctx.span_between(tokens, remain),
         };
-        Ok((tokens, ParameterDefinition(param_type, param_name)))
+        Ok((
+            remain,
+            ParameterDefinition(declarator.ty, declarator.name, span),
+        ))
+    }
+
+    /// Parse a parameter list after its `(`, up to and including the `)`.
+    /// The names of the parameters go out of scope at its end.
+    pub fn parse_list<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Vec<ParameterDefinition>)> {
+        let mut params = Vec::new();
+        match tokens {
+            [Token::Keyword(Keyword::Void), Token::Symbol(Symbol::RightParen), remain @ ..]
+            | [Token::Symbol(Symbol::RightParen), remain @ ..] => return Ok((remain, params)),
+            _ => {}
+        }
+        ctx.in_scope(|ctx| {
+            let mut tokens = tokens;
+            loop {
+                let (remain, param) = ParameterDefinition::parse(tokens, ctx)?;
+                params.push(param);
+                let (after_param, token_after_param) = next(remain)?;
+                match token_after_param {
+                    Token::Symbol(Symbol::Comma) => tokens = after_param,
+                    Token::Symbol(Symbol::RightParen) => break Ok((after_param, params)),
+                    _ => return Err(expected(ctx, remain, "`,` or `)`")),
+                }
+            }
+        })
     }
 }
@@ -1,30 +1,81 @@
 use crate::{
+    diagnostics::Span,
     lexical_analysis::{Keyword, Symbol, Token},
     syntax_analysis::next,
 };
 
 use super::{
     block_statement::Block,
+    declaration::Declaration,
+    expected,
     expression::{eval, Expression},
+    ParseContext,
 };
 
 #[derive(Debug)]
 pub enum Statement {
     If(Expression, Box<Statement>, Option<Box<Statement>>),
     While(Expression, Box<Statement>),
+    DoWhile(Box<Statement>, Expression),
+    /// `for (init; condition; step) body`; missing clauses are `None`.
+    For(
+        Option<ForInit>,
+        Option<Expression>,
+        Option<Expression>,
+        Box<Statement>,
+    ),
+    /// `switch (value) body`, with the span of the `switch` keyword.
+    Switch(Expression, Box<Statement>, Span),
+    /// `case value: statement`, with the span of the label.
+    Case(Expression, Box<Statement>, Span),
+    /// `default: statement`, with the span of the label.
+    Default(Box<Statement>, Span),
+    /// `label: statement`, with the span of the label name.
+    Labeled(String, Box<Statement>, Span),
     Block(Block),
     Expression(Expression),
     Jump(JumpStatement),
     Empty,
+    /// A statement with syntax errors, skipped by error recovery.
+    Error(Span),
+}
+
+/// First clause of a `for` statement. A declared variable is only visible
+/// in the loop.
+#[derive(Debug)]
+pub enum ForInit {
+    Declaration(Vec<Declaration>),
+    Expression(Expression),
 }
 
 #[derive(Debug)]
 pub enum JumpStatement {
-    Return(Option<Expression>),
+    Break(Span),
+    Continue(Span),
+    /// `goto label;`, with the span of the label name.
+    Goto(String, Span),
+    Return(Option<Expression>, Span),
 }
 
 impl Statement {
-    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
+    /// Parse a statement, recovering from syntax errors inside it.
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        match Statement::parse_inner(tokens, ctx) {
+            Ok(result) => Ok(result),
+            Err(err) => {
+                let (remain, span) = ctx.recover(err, tokens)?;
+                Ok((remain, Statement::Error(span)))
+            }
+        }
+    }
+
+    fn parse_inner<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
         match tokens {
             [] => unreachable!(),
             [Token::Symbol(Symbol::Semicolon), remain_tokens @ ..] => {
@@ -32,16 +83,16 @@ impl Statement {
             }
             [Token::Keyword(Keyword::If), tokens @ ..] => {
                 let (tokens, Token::Symbol(Symbol::LeftParen)) = next(tokens)? else {
-                    anyhow::bail!("Expected \"(\"");
+                    return Err(expected(ctx, tokens, "`(`"));
                 };
-                let (tokens, condition) = Expression::parse(tokens)?;
+                let (tokens, condition) = Expression::parse(tokens, ctx)?;
                 let (tokens, Token::Symbol(Symbol::RightParen)) = next(tokens)? else {
-                    anyhow::bail!("Expected \")\"");
+                    return Err(expected(ctx, tokens, "`)`"));
                 };
-                let (tokens, true_branch) = Statement::parse(tokens)?;
+                let (tokens, true_branch) = Statement::parse(tokens, ctx)?;
                 match tokens {
                     [Token::Keyword(Keyword::Else), tokens @ ..] => {
-                        let (tokens, false_branch) = Statement::parse(tokens)?;
+                        let (tokens, false_branch) = Statement::parse(tokens, ctx)?;
                         Ok((
                             tokens,
                             Statement::If(
@@ -59,41 +110,175 @@ impl Statement {
             }
             [Token::Keyword(Keyword::While), tokens @ ..] => {
                 let (tokens, Token::Symbol(Symbol::LeftParen)) = next(tokens)? else {
-                    anyhow::bail!("Expected \"(\"");
+                    return Err(expected(ctx, tokens, "`(`"));
                 };
-                let (tokens, condition) = Expression::parse(tokens)?;
+                let (tokens, condition) = Expression::parse(tokens, ctx)?;
                 let (tokens, Token::Symbol(Symbol::RightParen)) = next(tokens)? else {
-                    anyhow::bail!("Expected \")\"");
+                    return Err(expected(ctx, tokens, "`)`"));
                 };
-                let (tokens, body) = Statement::parse(tokens)?;
+                let (tokens, body) = Statement::parse(tokens, ctx)?;
                 Ok((tokens, Statement::While(eval(condition), Box::new(body))))
             }
+            [Token::Keyword(Keyword::Do), tokens @ ..] => {
+                let (tokens, body) = Statement::parse(tokens, ctx)?;
+                let (tokens, Token::Keyword(Keyword::While)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`while`"));
+                };
+                let (tokens, Token::Symbol(Symbol::LeftParen)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`(`"));
+                };
+                let (tokens, condition) = Expression::parse(tokens, ctx)?;
+                let (tokens, Token::Symbol(Symbol::RightParen)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`)`"));
+                };
+                let (tokens, Token::Symbol(Symbol::Semicolon)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`;`"));
+                };
+                Ok((tokens, Statement::DoWhile(Box::new(body), eval(condition))))
+            }
+            [Token::Keyword(Keyword::For), tokens @ ..] => {
+                ctx.in_scope(|ctx| Statement::parse_for(tokens, ctx))
+            }
+            [Token::Keyword(Keyword::Switch), remain @ ..] => {
+                let (remain, Token::Symbol(Symbol::LeftParen)) = next(remain)? else {
+                    return Err(expected(ctx, remain, "`(`"));
+                };
+                let (remain, value) = Expression::parse(remain, ctx)?;
+                let (remain, Token::Symbol(Symbol::RightParen)) = next(remain)? else {
+                    return Err(expected(ctx, remain, "`)`"));
+                };
+                let (remain, body) = Statement::parse(remain, ctx)?;
+                Ok((
+                    remain,
+                    Statement::Switch(eval(value), Box::new(body), ctx.span(tokens)),
+                ))
+            }
+            [Token::Keyword(Keyword::Case), remain @ ..] => {
+                let (remain, value) = Expression::parse_conditional(remain, ctx)?;
+                let (remain, Token::Symbol(Symbol::Colon)) = next(remain)? else {
+                    return Err(expected(ctx, remain, "`:`"));
+                };
+                let span = ctx.span_between(tokens, remain);
+                let (remain, stmt) = Statement::parse(remain, ctx)?;
+                Ok((remain, Statement::Case(value, Box::new(stmt), span)))
+            }
+            [Token::Keyword(Keyword::Default), remain @ ..] => {
+                let (remain, Token::Symbol(Symbol::Colon)) = next(remain)? else {
+                    return Err(expected(ctx, remain, "`:`"));
+                };
+                let span = ctx.span_between(tokens, remain);
+                let (remain, stmt) = Statement::parse(remain, ctx)?;
+                Ok((remain, Statement::Default(Box::new(stmt), span)))
+            }
             [Token::Symbol(Symbol::LeftBrace), ..] => {
-                let (tokens, block) = Block::parse(tokens)?;
+                let (tokens, block) = Block::parse(tokens, ctx)?;
                 Ok((tokens, Statement::Block(block)))
             }
-            [Token::Keyword(Keyword::Return), tokens @ ..] => match tokens {
-                [Token::Symbol(Symbol::Semicolon), tokens @ ..] => {
-                    Ok((tokens, Statement::Jump(JumpStatement::Return(None))))
+            [Token::Keyword(keyword @ (Keyword::Break | Keyword::Continue)), remain @ ..] => {
+                let (remain, Token::Symbol(Symbol::Semicolon)) = next(remain)? else {
+                    return Err(expected(ctx, remain, "`;`"));
+                };
+                let span = ctx.span(tokens);
+                let jump = match keyword {
+                    Keyword::Break => JumpStatement::Break(span),
+                    _ => JumpStatement::Continue(span),
+                };
+                Ok((remain, Statement::Jump(jump)))
+            }
+            [Token::Keyword(Keyword::Goto), remain @ ..] => {
+                let [Token::Identifier(label), after_label @ ..] = remain else {
+                    return Err(expected(ctx, remain, "label"));
+                };
+                let span = ctx.span(remain);
+                let (after_label, Token::Symbol(Symbol::Semicolon)) = next(after_label)? else {
+                    return Err(expected(ctx, after_label, "`;`"));
+                };
+                Ok((
+                    after_label,
+                    Statement::Jump(JumpStatement::Goto(label.to_owned(), span)),
+                ))
+            }
+            [Token::Identifier(label), Token::Symbol(Symbol::Colon), remain @ ..] => {
+                let (remain, stmt) = Statement::parse(remain, ctx)?;
+                Ok((
+                    remain,
+                    Statement::Labeled(label.to_owned(), Box::new(stmt), ctx.span(tokens)),
+                ))
+            }
+            [Token::Keyword(Keyword::Return), remain @ ..] => match remain {
+                [Token::Symbol(Symbol::Semicolon), remain @ ..] => {
+                    let span = ctx.span_between(tokens, remain);
+                    Ok((remain, Statement::Jump(JumpStatement::Return(None, span))))
                 }
                 _ => {
-                    let (tokens, return_value) = Expression::parse(tokens)?;
-                    let (tokens, Token::Symbol(Symbol::Semicolon)) = next(tokens)? else {
-                        anyhow::bail!("Expected \";\"");
+                    let (remain, return_value) = Expression::parse(remain, ctx)?;
+                    let (remain, Token::Symbol(Symbol::Semicolon)) = next(remain)? else {
+                        return Err(expected(ctx, remain, "`;`"));
                     };
+                    let span = ctx.span_between(tokens, remain);
                     Ok((
-                        tokens,
-                        Statement::Jump(JumpStatement::Return(Some(eval(return_value)))),
+                        remain,
+                        Statement::Jump(JumpStatement::Return(Some(eval(return_value)), span)),
                     ))
                 }
-            }
+            },
             _ => {
-                let (tokens, expr) = Expression::parse(tokens)?;
+                let (tokens, expr) = Expression::parse(tokens, ctx)?;
                 let (tokens, Token::Symbol(Symbol::Semicolon)) = next(tokens)? else {
-                    anyhow::bail!("Expected \";\"");
+                    return Err(expected(ctx, tokens, "`;`"));
                 };
                 Ok((tokens, Statement::Expression(expr)))
             }
         }
     }
+
+    /// Parse what follows `for`. A declaration in the first clause is in
+    /// scope for the rest of the statement only.
+    fn parse_for<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (tokens, Token::Symbol(Symbol::LeftParen)) = next(tokens)? else {
+            return Err(expected(ctx, tokens, "`(`"));
+        };
+        let (tokens, init) = match tokens {
+            [Token::Symbol(Symbol::Semicolon), tokens @ ..] => (tokens, None),
+            _ if ctx.starts_declaration(tokens) => {
+                let (tokens, decls) = Declaration::parse(tokens, ctx)?;
+                (tokens, Some(ForInit::Declaration(decls)))
+            }
+            _ => {
+                let (tokens, init) = Expression::parse(tokens, ctx)?;
+                let (tokens, Token::Symbol(Symbol::Semicolon)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`;`"));
+                };
+                (tokens, Some(ForInit::Expression(init)))
+            }
+        };
+        let (tokens, condition) = match tokens {
+            [Token::Symbol(Symbol::Semicolon), tokens @ ..] => (tokens, None),
+            _ => {
+                let (tokens, condition) = Expression::parse(tokens, ctx)?;
+                let (tokens, Token::Symbol(Symbol::Semicolon)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`;`"));
+                };
+                (tokens, Some(eval(condition)))
+            }
+        };
+        let (tokens, step) = match tokens {
+            [Token::Symbol(Symbol::RightParen), tokens @ ..] => (tokens, None),
+            _ => {
+                let (tokens, step) = Expression::parse(tokens, ctx)?;
+                let (tokens, Token::Symbol(Symbol::RightParen)) = next(tokens)? else {
+                    return Err(expected(ctx, tokens, "`)`"));
+                };
+                (tokens, Some(step))
+            }
+        };
+        let (tokens, body) = Statement::parse(tokens, ctx)?;
+        Ok((
+            tokens,
+            Statement::For(init, condition, step, Box::new(body)),
+        ))
+    }
 }
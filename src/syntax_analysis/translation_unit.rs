@@ -1,25 +1,41 @@
-use crate::lexical_analysis::{Keyword, Token};
+use crate::lexical_analysis::Token;
 
-use super::declaration::Declaration;
+use super::{declaration::Declaration, expected, ParseContext, ParseError};
 
 #[derive(Debug)]
 pub struct TranslationUnit(pub Vec<Declaration>);
 
 impl TranslationUnit {
-    pub fn parse(mut tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
+    pub fn parse<'a>(
+        mut tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
         let mut body = Vec::new();
         loop {
             match tokens {
-                [] => unreachable!(),
+                [] => anyhow::bail!(ParseError::Internal {
+                    message: "Expected token".to_owned(),
+                }),
                 [Token::End, tokens @ ..] => {
                     break Ok((tokens, Self(body)));
                 }
-                [Token::Keyword(Keyword::Int | Keyword::Void), ..] => {
-                    let (remain, decl) = Declaration::parse(tokens)?;
-                    body.push(decl);
+                _ if ctx.starts_declaration(tokens) => {
+                    let (remain, decls) = match Declaration::parse(tokens, ctx) {
+                        Ok(result) => result,
+                        Err(err) => {
+                            let (remain, span) = ctx.recover(err, tokens)?;
+                            (remain, vec![Declaration::Error(span)])
+                        }
+                    };
+                    body.extend(decls);
+                    tokens = remain;
+                }
+                [_, ..] => {
+                    let err = expected(ctx, tokens, "declaration");
+                    let (remain, span) = ctx.recover(err, tokens)?;
+                    body.push(Declaration::Error(span));
                     tokens = remain;
                 }
-                [_, ..] => anyhow::bail!("Expected declaration"),
             }
         }
     }
@@ -1,24 +1,532 @@
-use crate::lexical_analysis::{Keyword, Token};
+use crate::{
+    diagnostics::Span,
+    lexical_analysis::{Keyword, Symbol, Token},
+};
 
-#[derive(Debug)]
+use super::{
+    declarator::Declarator, expected, expression::Expression, next, ParseContext, ParseError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     Void,
+    Integer(IntegerType),
+    Pointer(Box<Type>),
+    Array(Box<Type>, ArrayLength),
+    Record(RecordType),
+    /// A function type: the return type and the parameter types.
+    Function(Box<Type>, Vec<Type>),
+    /// A structure or union as written, resolved to a `Record` by semantic
+    /// analysis.
+    RecordSpecifier(Box<RecordSpecifier>),
+    /// An enumeration as written, resolved to its underlying integer type by
+    /// semantic analysis.
+    EnumSpecifier(Box<EnumSpecifier>),
+    /// A typedef name, resolved to the type it names by semantic analysis.
+    Typedef(String),
+}
+
+/// Number of elements of an array type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArrayLength {
+    /// `[]`, completed by an initializer.
+    Unknown,
+    /// A constant expression, evaluated by semantic analysis.
+    Expression(Box<Expression>),
+    Known(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Struct,
+    Union,
+}
+
+/// A structure or union type, identified by its declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordType {
+    pub kind: RecordKind,
+    pub tag: Option<String>,
+    /// Index in the type table of semantic analysis.
+    pub id: usize,
+}
+
+/// `struct tag`, `struct tag { members }` or `struct { members }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordSpecifier {
+    pub kind: RecordKind,
+    pub tag: Option<String>,
+    /// The members, if this is a definition.
+    pub members: Option<Vec<Member>>,
+    /// Span of the tag, or of the keyword if there is none.
+    pub span: Span,
+}
+
+/// A member of a structure or union. Anonymous structures and unions have no
+/// name; their members are accessed as members of the enclosing one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member(pub Type, pub Option<String>, pub Span);
+
+/// `enum tag`, `enum tag { enumerators }` or `enum { enumerators }`, with an
+/// optional fixed underlying type as in `enum tag : unsigned char`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumSpecifier {
+    pub tag: Option<String>,
+    /// An integer type, or a typedef name for one.
+    pub underlying: Option<Type>,
+    /// The enumerators, if this is a definition.
+    pub enumerators: Option<Vec<Enumerator>>,
+    /// Span of the tag, or of the keyword if there is none.
+    pub span: Span,
+}
+
+/// An enumeration constant, and the expression for its value if given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enumerator(pub String, pub Option<Expression>, pub Span);
+
+/// Integer types, ordered by conversion rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntegerKind {
+    Bool,
+    Char,
+    Short,
     Int,
+    Long,
+    LongLong,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegerType {
+    pub kind: IntegerKind,
+    pub signed: bool,
+}
+
+impl IntegerType {
+    pub const INT: IntegerType = IntegerType {
+        kind: IntegerKind::Int,
+        signed: true,
+    };
+
+    /// Width in bits, for an LP64 target. `_Bool` is stored in a byte.
+    pub fn bits(self) -> u32 {
+        match self.kind {
+            IntegerKind::Bool | IntegerKind::Char => 8,
+            IntegerKind::Short => 16,
+            IntegerKind::Int => 32,
+            IntegerKind::Long | IntegerKind::LongLong => 64,
+        }
+    }
 }
 
 impl Type {
-    pub fn parse(tokens: &[Token]) -> anyhow::Result<(&[Token], Self)> {
-        match tokens {
-            [Token::Keyword(keyword), ..] => {
-                let kwtype = match keyword {
-                    Keyword::Int => Type::Int,
-                    Keyword::Void => Type::Void,
-                    _ => anyhow::bail!("Expected type"),
+    /// Parse a list of type specifiers, such as `unsigned long int`, in any
+    /// order, or a typedef name.
+    pub fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        if let [Token::Identifier(name), remain @ ..] = tokens {
+            if ctx.is_typedef_name(name) {
+                return Ok((remain, Type::Typedef(name.to_owned())));
+            }
+        }
+        if let [Token::Keyword(keyword @ (Keyword::Struct | Keyword::Union)), remain @ ..] = tokens
+        {
+            let kind = match keyword {
+                Keyword::Struct => RecordKind::Struct,
+                _ => RecordKind::Union,
+            };
+            let (remain, specifier) = RecordSpecifier::parse(tokens, remain, ctx, kind)?;
+            return Ok((remain, Type::RecordSpecifier(Box::new(specifier))));
+        }
+        if let [Token::Keyword(Keyword::Enum), remain @ ..] = tokens {
+            let (remain, specifier) = EnumSpecifier::parse(tokens, remain, ctx)?;
+            return Ok((remain, Type::EnumSpecifier(Box::new(specifier))));
+        }
+        let mut remain = tokens;
+        // number of `void`, `_Bool`, `char`, `short`, `int` and `long`
+        let mut counts = [0; 6];
+        let mut signed = None;
+        let mut sign_count = 0;
+        while let [Token::Keyword(keyword), after @ ..] = remain {
+            match keyword {
+                Keyword::Void => counts[0] += 1,
+                Keyword::Bool => counts[1] += 1,
+                Keyword::Char => counts[2] += 1,
+                Keyword::Short => counts[3] += 1,
+                Keyword::Int => counts[4] += 1,
+                Keyword::Long => counts[5] += 1,
+                Keyword::Signed | Keyword::Unsigned => {
+                    sign_count += 1;
+                    signed = Some(matches!(keyword, Keyword::Signed));
+                }
+                _ => break,
+            }
+            remain = after;
+        }
+        if remain.len() == tokens.len() {
+            return Err(expected(ctx, tokens, "type"));
+        }
+        let kind = match counts {
+            [1, 0, 0, 0, 0, 0] if sign_count == 0 => return Ok((remain, Type::Void)),
+            [0, 1, 0, 0, 0, 0] if sign_count == 0 => IntegerKind::Bool,
+            [0, 0, 1, 0, 0, 0] => IntegerKind::Char,
+            [0, 0, 0, 1, 0 | 1, 0] => IntegerKind::Short,
+            [0, 0, 0, 0, 0 | 1, 0] => IntegerKind::Int,
+            [0, 0, 0, 0, 0 | 1, 1] => IntegerKind::Long,
+            [0, 0, 0, 0, 0 | 1, 2] => IntegerKind::LongLong,
+            _ => anyhow::bail!(ParseError::InvalidTypeSpecifiers {
+                span: ctx.span_between(tokens, remain),
+            }),
+        };
+        if sign_count > 1 {
+            anyhow::bail!(ParseError::InvalidTypeSpecifiers {
+                span: ctx.span_between(tokens, remain),
+            });
+        }
+        let signed = match kind {
+            IntegerKind::Bool => false,
+            // plain `char` is signed on our targets
+            _ => signed.unwrap_or(true),
+        };
+        Ok((remain, Type::Integer(IntegerType { kind, signed })))
+    }
+
+    /// Parse a type name, as in a cast: type specifiers followed by an
+    /// abstract declarator.
+    pub fn parse_type_name<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (remain, ty) = Type::parse(tokens, ctx)?;
+        Declarator::parse_abstract(remain, ctx, ty)
+    }
+}
+
+impl RecordSpecifier {
+    /// Parse what follows `struct` or `union`, the first token of `tokens`.
+    fn parse<'a>(
+        tokens: &'a [Token],
+        after_keyword: &'a [Token],
+        ctx: &mut ParseContext,
+        kind: RecordKind,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (remain, tag, span) = match after_keyword {
+            [Token::Identifier(tag), remain @ ..] => {
+                (remain, Some(tag.to_owned()), ctx.span(after_keyword))
+            }
+            [Token::Symbol(Symbol::LeftBrace), ..] => (after_keyword, None, ctx.span(tokens)),
+            _ => return Err(expected(ctx, after_keyword, "identifier or `{`")),
+        };
+        let [Token::Symbol(Symbol::LeftBrace), after_brace @ ..] = remain else {
+            let specifier = RecordSpecifier {
+                kind,
+                tag,
+                members: None,
+                span,
+            };
+            return Ok((remain, specifier));
+        };
+        let mut remain = after_brace;
+        let mut members = Vec::new();
+        while !matches!(remain, [Token::Symbol(Symbol::RightBrace), ..]) {
+            remain = Member::parse(remain, ctx, &mut members)?;
+        }
+        let specifier = RecordSpecifier {
+            kind,
+            tag,
+            members: Some(members),
+            span,
+        };
+        Ok((&remain[1..], specifier))
+    }
+}
+
+impl EnumSpecifier {
+    /// Parse what follows `enum`, the first token of `tokens`.
+    fn parse<'a>(
+        tokens: &'a [Token],
+        after_keyword: &'a [Token],
+        ctx: &mut ParseContext,
+    ) -> anyhow::Result<(&'a [Token], Self)> {
+        let (mut remain, tag, span) = match after_keyword {
+            [Token::Identifier(tag), remain @ ..] => {
+                (remain, Some(tag.to_owned()), ctx.span(after_keyword))
+            }
+            [Token::Symbol(Symbol::LeftBrace | Symbol::Colon), ..] => {
+                (after_keyword, None, ctx.span(tokens))
+            }
+            _ => return Err(expected(ctx, after_keyword, "identifier, `:` or `{`")),
+        };
+        let mut underlying = None;
+        if let [Token::Symbol(Symbol::Colon), after_colon @ ..] = remain {
+            let (after_type, ty) = Type::parse(after_colon, ctx)?;
+            if !matches!(ty, Type::Integer(_) | Type::Typedef(_)) {
+                return Err(expected(ctx, after_colon, "integer type"));
+            }
+            underlying = Some(ty);
+            remain = after_type;
+        }
+        let [Token::Symbol(Symbol::LeftBrace), after_brace @ ..] = remain else {
+            if tag.is_none() {
+                return Err(expected(ctx, remain, "`{`"));
+            }
+            let specifier = EnumSpecifier {
+                tag,
+                underlying,
+                enumerators: None,
+                span,
+            };
+            return Ok((remain, specifier));
+        };
+        let mut remain = after_brace;
+        let mut enumerators = Vec::new();
+        loop {
+            let (after_name, Token::Identifier(name)) = next(remain)? else {
+                return Err(expected(ctx, remain, "enumerator"));
+            };
+            let name_span = ctx.span(remain);
+            ctx.declare(&name, false);
+            let (after_value, value) = match after_name {
+                [Token::Symbol(Symbol::Equal), after_assign @ ..] => {
+                    let (after_value, value) = Expression::parse_conditional(after_assign, ctx)?;
+                    (after_value, Some(value))
+                }
+                _ => (after_name, None),
+            };
+            enumerators.push(Enumerator(name, value, name_span));
+            remain = match after_value {
+                [Token::Symbol(Symbol::Comma), Token::Symbol(Symbol::RightBrace), after @ ..]
+                | [Token::Symbol(Symbol::RightBrace), after @ ..] => {
+                    break Ok((
+                        after,
+                        EnumSpecifier {
+                            tag,
+                            underlying,
+                            enumerators: Some(enumerators),
+                            span,
+                        },
+                    ))
+                }
+                [Token::Symbol(Symbol::Comma), after @ ..] => after,
+                _ => return Err(expected(ctx, after_value, "`,` or `}`")),
+            };
+        }
+    }
+}
+
+impl Member {
+    /// Parse a member declaration, which may declare several members, such
+    /// as `int x, *p;`.
+    fn parse<'a>(
+        tokens: &'a [Token],
+        ctx: &mut ParseContext,
+        members: &mut Vec<Member>,
+    ) -> anyhow::Result<&'a [Token]> {
+        let (mut remain, member_type) = Type::parse(tokens, ctx)?;
+        if let [Token::Symbol(Symbol::Semicolon), after @ ..] = remain {
+            if let Type::RecordSpecifier(specifier) = &member_type {
+                if specifier.tag.is_none() {
+                    members.push(Member(member_type, None, ctx.span(tokens)));
+                    return Ok(after);
+                }
+            }
+        }
+        loop {
+            let (after_declarator, declarator) =
+                Declarator::parse(remain, ctx, member_type.clone())?;
+            members.push(Member(declarator.ty, declarator.name, declarator.span));
+            remain = match after_declarator {
+                [Token::Symbol(Symbol::Comma), after @ ..] => after,
+                [Token::Symbol(Symbol::Semicolon), after @ ..] => return Ok(after),
+                _ => return Err(expected(ctx, after_declarator, "`,` or `;`")),
+            };
+        }
+    }
+}
+
+impl std::fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordKind::Struct => write!(f, "struct"),
+            RecordKind::Union => write!(f, "union"),
+        }
+    }
+}
+
+impl Type {
+    /// The abstract declarator that derives this type, built inside out
+    /// around `inner`, and the type it derives from.
+    fn declarator(&self, inner: String) -> (&Type, String) {
+        // a pointer binds looser than an array or function suffix
+        let parenthesized = |inner: String| match inner.starts_with('*') {
+            true => format!("({inner})"),
+            false => inner,
+        };
+        match self {
+            Type::Pointer(pointee) => pointee.declarator(format!("*{inner}")),
+            Type::Array(element, length) => {
+                let inner = parenthesized(inner);
+                match length {
+                    ArrayLength::Known(length) => element.declarator(format!("{inner}[{length}]")),
+                    ArrayLength::Expression(length) => {
+                        element.declarator(format!("{inner}[{length}]"))
+                    }
+                    ArrayLength::Unknown => element.declarator(format!("{inner}[]")),
+                }
+            }
+            Type::Function(return_type, parameters) => {
+                let parameters = match parameters.is_empty() {
+                    true => "void".to_owned(),
+                    false => parameters
+                        .iter()
+                        .map(Type::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
                 };
-                Ok((&tokens[1..], kwtype))
+                let inner = parenthesized(inner);
+                return_type.declarator(format!("{inner}({parameters})"))
             }
-            [_, ..] => anyhow::bail!("Expected type"),
-            [] => unreachable!(),
+            _ => (self, inner),
         }
     }
 }
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (base, declarator) = self.declarator(String::new());
+        match base {
+            Type::Void => write!(f, "void")?,
+            Type::Integer(IntegerType { kind, signed }) => {
+                if !signed && *kind != IntegerKind::Bool {
+                    write!(f, "unsigned ")?;
+                }
+                let name = match kind {
+                    IntegerKind::Bool => "_Bool",
+                    IntegerKind::Char => "char",
+                    IntegerKind::Short => "short",
+                    IntegerKind::Int => "int",
+                    IntegerKind::Long => "long",
+                    IntegerKind::LongLong => "long long",
+                };
+                write!(f, "{name}")?
+            }
+            Type::Record(RecordType { kind, tag, .. }) => match tag {
+                Some(tag) => write!(f, "{kind} {tag}")?,
+                None => write!(f, "{kind} <anonymous>")?,
+            },
+            Type::RecordSpecifier(specifier) => match &specifier.tag {
+                Some(tag) => write!(f, "{} {tag}", specifier.kind)?,
+                None => write!(f, "{} <anonymous>", specifier.kind)?,
+            },
+            Type::EnumSpecifier(specifier) => match &specifier.tag {
+                Some(tag) => write!(f, "enum {tag}")?,
+                None => write!(f, "enum <anonymous>")?,
+            },
+            Type::Typedef(name) => write!(f, "{name}")?,
+            Type::Pointer(_) | Type::Array(..) | Type::Function(..) => {
+                unreachable!("derived types are written as declarators")
+            }
+        }
+        // as in `int *`, `int (*)[3]` and `int[3]`
+        if declarator.starts_with(['*', '(']) {
+            write!(f, " ")?;
+        }
+        write!(f, "{declarator}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical_analysis::extract_tokens;
+
+    fn parse_type(code: &str) -> Result<Type, ParseError> {
+        let tokens = extract_tokens(code).unwrap();
+        let mut ctx = ParseContext::new(&tokens.spans);
+        Type::parse(&tokens.tokens, &mut ctx)
+            .map(|(_, parsed)| parsed)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[test]
+    fn type_specifiers_in_any_order() {
+        for (code, expected) in [
+            ("int", "int"),
+            ("unsigned", "unsigned int"),
+            ("long unsigned int", "unsigned long"),
+            ("long long", "long long"),
+            ("int long signed long", "long long"),
+            ("unsigned char", "unsigned char"),
+            ("short int", "short"),
+            ("_Bool", "_Bool"),
+            ("void", "void"),
+        ] {
+            assert_eq!(parse_type(code).unwrap().to_string(), expected, "{code}");
+        }
+        for code in [
+            "long long long",
+            "signed unsigned",
+            "short char",
+            "unsigned void",
+        ] {
+            assert!(
+                matches!(
+                    parse_type(code),
+                    Err(ParseError::InvalidTypeSpecifiers { .. })
+                ),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn record_members_in_order() {
+        let Type::RecordSpecifier(specifier) =
+            parse_type("struct s { int x, *p; union { char c; long l; }; struct s *next; }")
+                .unwrap()
+        else {
+            panic!("not a structure");
+        };
+        assert_eq!(specifier.kind, RecordKind::Struct);
+        assert_eq!(specifier.tag.as_deref(), Some("s"));
+        let members = specifier.members.unwrap();
+        let names: Vec<_> = members
+            .iter()
+            .map(|Member(_, name, _)| name.as_deref())
+            .collect();
+        assert_eq!(names, [Some("x"), Some("p"), None, Some("next")]);
+        assert_eq!(members[1].0.to_string(), "int *");
+        assert_eq!(members[3].0.to_string(), "struct s *");
+        assert!(
+            matches!(parse_type("struct s").unwrap(), Type::RecordSpecifier(s) if s.members.is_none())
+        );
+        assert!(parse_type("union { int x }").is_err());
+    }
+
+    #[test]
+    fn enumerators_with_fixed_underlying_type() {
+        let Type::EnumSpecifier(specifier) =
+            parse_type("enum e : unsigned char { A, B = 3, C, }").unwrap()
+        else {
+            panic!("not an enumeration");
+        };
+        assert_eq!(specifier.tag.as_deref(), Some("e"));
+        assert_eq!(
+            specifier.underlying,
+            Some(Type::Integer(IntegerType {
+                kind: IntegerKind::Char,
+                signed: false,
+            }))
+        );
+        let enumerators = specifier.enumerators.unwrap();
+        let names: Vec<_> = enumerators
+            .iter()
+            .map(|Enumerator(name, value, _)| (name.as_str(), value.is_some()))
+            .collect();
+        assert_eq!(names, [("A", false), ("B", true), ("C", false)]);
+        assert!(parse_type("enum {}").is_err());
+        assert!(parse_type("enum : int").is_err());
+        assert!(parse_type("enum e : struct s { int x; }").is_err());
+    }
+}